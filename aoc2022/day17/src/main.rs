@@ -1,189 +1,346 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+/// Gap between the left wall and the left edge of a new rock
+const SPAWN_X: usize = 2;
+/// Number of empty rows between the highest rock and a new rock
+const SPAWN_GAP: usize = 3;
+/// The chamber rows are `u16` bitmasks
+const MAX_WIDTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Shape {
-    pub parts: HashSet<(u64, u64)>,
+    /// One bitmask per row, bottom row first. Bit `i` is the
+    /// `i`-th column from the left edge of the shape.
+    pub rows: Vec<u16>,
+    pub width: usize,
 }
 
 impl Shape {
-    fn horizontal(height: u64) -> Self {
-        Self {
-            parts: HashSet::from([
-                (2, height + 3),
-                (3, height + 3),
-                (4, height + 3),
-                (5, height + 3)
-            ]),
+    /// Parse a shape drawn with `#` and `.`, top row first.
+    fn from_ascii(lines: &[String]) -> Result<Self, String> {
+        if let Some(line) = lines.iter().find(|line| line.chars().count() > MAX_WIDTH) {
+            return Err(format!(
+                "Shape line {} is wider than {} columns",
+                line, MAX_WIDTH
+            ));
         }
+        let rows = lines
+            .iter()
+            .rev()
+            .map(|line| {
+                line.chars()
+                    .enumerate()
+                    .try_fold(0u16, |row, (ix, c)| match c {
+                        '#' => Ok(row | (1 << ix)),
+                        '.' => Ok(row),
+                        _ => Err(format!("Unexpected character {} in shape", c)),
+                    })
+            })
+            .collect::<Result<Vec<u16>, _>>()?;
+        let width = rows
+            .iter()
+            .map(|row| (u16::BITS - row.leading_zeros()) as usize)
+            .max()
+            .unwrap_or_default();
+        Ok(Self { rows, width })
     }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Jet {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+struct RockFall<'a> {
+    pub width: usize,
+    pub shapes: &'a [Shape],
+    pub jets: &'a [Jet],
+    /// Resting rocks, one bitmask per row starting from the floor
+    pub rows: Vec<u16>,
+    pub num: u64,
+    shape_ix: usize,
+    jet_ix: usize,
+}
 
-    fn plus(height: u64) -> Self {
-        Self {
-            parts: HashSet::from([
-                (2, height + 4),
-                (3, height + 3),
-                (3, height + 4),
-                (3, height + 5),
-                (4, height + 4),
-            ])
+impl<'a> RockFall<'a> {
+    fn new(width: usize, shapes: &'a [Shape], jets: &'a [Jet]) -> Result<Self, String> {
+        if width > MAX_WIDTH {
+            return Err(format!("Chamber can be at most {} wide", MAX_WIDTH));
+        }
+        if shapes.is_empty() || jets.is_empty() {
+            return Err("There must be at least one shape and one jet".to_string());
+        }
+        if let Some(shape) = shapes.iter().find(|shape| SPAWN_X + shape.width > width) {
+            return Err(format!("Shape {:?} does not fit in the chamber", shape));
         }
+        Ok(Self {
+            width,
+            shapes,
+            jets,
+            rows: vec![],
+            num: 0,
+            shape_ix: 0,
+            jet_ix: 0,
+        })
     }
 
-    fn ell(height: u64) -> Self {
-        Self {
-            parts: HashSet::from([
-                (2, height + 3),
-                (3, height + 3),
-                (4, height + 3),
-                (4, height + 4),
-                (4, height + 5),
-            ])
-        }
+    fn height(&self) -> u64 {
+        self.rows.len() as u64
     }
 
-    fn vertical(height: u64) -> Self {
-        Self {
-            parts: HashSet::from([
-                (2, height + 6),
-                (2, height + 5),
-                (2, height + 4),
-                (2, height + 3)
-            ]),
-        }
+    /// Check if a shape with its bottom left corner at (x, y) overlaps
+    /// with any resting rocks
+    fn collides(&self, shape: &Shape, x: usize, y: usize) -> bool {
+        shape
+            .rows
+            .iter()
+            .zip(self.rows.iter().skip(y))
+            .any(|(part, row)| (part << x) & row != 0)
     }
 
-    fn square(height: u64) -> Self {
-        Self {
-            parts: HashSet::from([
-                (2, height + 3),
-                (3, height + 3),
-                (2, height + 4),
-                (3, height + 4),
-            ])
+    /// Shape gets pushed by jets respecting walls. Returns the new x coordinate.
+    fn push(&self, shape: &Shape, x: usize, y: usize, jet: Jet) -> usize {
+        match jet {
+            Jet::Left if x > 0 && !self.collides(shape, x - 1, y) => x - 1,
+            Jet::Right if x + shape.width < self.width && !self.collides(shape, x + 1, y) => {
+                x + 1
+            }
+            _ => x,
         }
     }
-}
-
-#[derive(Debug, Default)]
-struct RockFall {
-    pub height: u64,
-    pub num: u64,
-    pub rocks: HashSet<(u64, u64)>
-}
-
-impl RockFall {
 
+    /// Drop the next rock until it comes to rest
+    fn drop_rock(&mut self) {
+        let shapes = self.shapes;
+        let shape = &shapes[self.shape_ix];
+        self.shape_ix = (self.shape_ix + 1) % shapes.len();
+        let mut x = SPAWN_X;
+        let mut y = self.rows.len() + SPAWN_GAP;
+        loop {
+            let jet = self.jets[self.jet_ix];
+            self.jet_ix = (self.jet_ix + 1) % self.jets.len();
+            x = self.push(shape, x, y, jet);
+            // shape is on the floor or ran into resting rocks
+            if y == 0 || self.collides(shape, x, y - 1) {
+                break;
+            }
+            y -= 1;
+        }
+        // insert new rocks and update the height
+        for (ix, part) in shape.rows.iter().enumerate() {
+            if y + ix == self.rows.len() {
+                self.rows.push(0);
+            }
+            self.rows[y + ix] |= part << x;
+        }
+        self.num += 1;
+    }
 
-    /// Shape gets pushed by jets respecting walls
-    fn push(&self, shape: &mut Shape, jet: char) {
-        match jet {
-            '<' => {
-                let moved: HashSet<(u64, u64)> = shape.parts
-                    .iter()
-                    .filter_map(|(x, y)|  x.checked_sub(1).map(|x| (x, *y)))
-                    .collect();
-                if moved.len() != shape.parts.len() || !self.rocks.is_disjoint(&moved) {
-                    return;
-                } else {
-                    shape.parts = moved;
+    /// The empty cells a falling rock could still reach, top row first.
+    /// Rocks only move sideways and down, so flood fill from the top in
+    /// those directions. Whatever lies below this surface can never
+    /// matter again.
+    fn surface(&self) -> Vec<u16> {
+        let walls = u16::MAX >> (MAX_WIDTH - self.width);
+        let mut surface = vec![];
+        let mut reached = walls;
+        for row in self.rows.iter().rev() {
+            let free = !row & walls;
+            reached &= free;
+            loop {
+                let spread = (reached | reached << 1 | reached >> 1) & free;
+                if spread == reached {
+                    break;
                 }
+                reached = spread;
             }
-            '>' => {
-                let moved: HashSet<(u64, u64)> = shape.parts
-                    .iter()
-                    .filter_map(|(x, y)| {
-                        if *x == 6 {
-                            None
-                        } else {
-                            Some((*x + 1, *y))
-                        }
-                    })
-                    .collect();
-                if moved.len() != shape.parts.len() || !self.rocks.is_disjoint(&moved) {
-                    return;
-                } else {
-                    shape.parts = moved;
-                }
+            if reached == 0 {
+                break;
             }
-            _ => {}
+            surface.push(reached);
         }
+        surface
     }
 
-    /// Rock falls one unit. Returns bool indicating if it came
-    /// to rest.
-    fn fall(&mut self, shape: &mut Shape) -> bool {
-        let moved: HashSet<(u64, u64)> = shape.parts
-            .iter()
-            .filter_map(|(x, y)| y.checked_sub(1).map(|y| (*x, y)))
-            .collect();
-        // shape was on the floor or ran into resting rocks
-        if moved.len() != shape.parts.len() || !moved.is_disjoint(&self.rocks) {
-            // insert new rocks and update the height
-            for rock in &shape.parts {
-                self.rocks.insert(*rock);
-                self.height = std::cmp::max(self.height, rock.1 + 1);
+    /// Everything that determines how the next rocks will fall
+    fn state(&self) -> (usize, usize, Vec<u16>) {
+        (self.shape_ix, self.jet_ix, self.surface())
+    }
+
+    /// Drop rocks until `num` have come to rest. Once the state at the top
+    /// of the chamber repeats, whole periods are skipped.
+    fn height_after(mut self, num: u64) -> u64 {
+        let mut seen = HashMap::new();
+        let mut skipped = None;
+        while self.num < num {
+            self.drop_rock();
+            if skipped.is_some() {
+                continue;
+            }
+            if let Some((prev_num, prev_height)) =
+                seen.insert(self.state(), (self.num, self.height()))
+            {
+                let period = self.num - prev_num;
+                let cycles = (num - self.num) / period;
+                self.num += cycles * period;
+                skipped = Some(cycles * (self.height() - prev_height));
             }
-            self.num += 1;
-            true
-        } else {
-            // shape moves down one unit
-            shape.parts = moved;
-            false
         }
+        self.height() + skipped.unwrap_or_default()
     }
-
 }
 
-fn parse_input(filename: &str) -> Vec<char> {
+fn parse_input(filename: &str) -> Vec<Jet> {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
     _ = reader.read_line(&mut line).unwrap();
-    line.chars().collect()
+    line.trim()
+        .chars()
+        .map(|c| match c {
+            '<' => Jet::Left,
+            '>' => Jet::Right,
+            _ => panic!("Unexpected jet {}", c),
+        })
+        .collect()
 }
 
-fn get_height(filename: &str, num: u64) -> u64 {
-    let jet_stream = parse_input(filename);
-    let stream_len = jet_stream.len();
-    let mut jet_ix = 0usize;
-    let mut shape_ix = 0usize;
-    let mut rockfall = RockFall::default();
-    let shapes = [Shape::horizontal, Shape::plus, Shape::ell, Shape::vertical, Shape::square];
-    loop {
-        // stop if the right number of rocks have come to rest
-        if rockfall.num == num {
-            break;
-        }
-        // get the next shape and advance the index
-        let mut next_rock = shapes[shape_ix](rockfall.height);
-        shape_ix = (shape_ix + 1).rem_euclid(5);
-        loop {
-            // get the next the next jet instruction and advance the index
-            let next_jet = jet_stream[jet_ix];
-            jet_ix = (jet_ix + 1).rem_euclid(stream_len);
-            // move the piece
-            rockfall.push(&mut next_rock, next_jet);
-            // if the rocks came to rest, move on to next rock
-            if rockfall.fall(&mut next_rock) {
-                break;
+/// Shapes are drawn in ASCII and separated by blank lines
+fn parse_shapes(filename: &str) -> Result<Vec<Shape>, String> {
+    let file = File::open(filename).unwrap();
+    let reader = BufReader::new(file);
+    let mut shapes = vec![];
+    let mut lines = vec![];
+    for line in reader.lines() {
+        let line = line.unwrap().trim().to_string();
+        if line.is_empty() {
+            if !lines.is_empty() {
+                shapes.push(Shape::from_ascii(&lines)?);
+                lines.clear();
             }
+        } else {
+            lines.push(line);
         }
     }
-    rockfall.height
+    if !lines.is_empty() {
+        shapes.push(Shape::from_ascii(&lines)?);
+    }
+    Ok(shapes)
+}
+
+fn get_height(filename: &str, shapes: &str, width: usize, num: u64) -> Result<u64, String> {
+    let jet_stream = parse_input(filename);
+    let shapes = parse_shapes(shapes)?;
+    Ok(RockFall::new(width, &shapes, &jet_stream)?.height_after(num))
 }
 
 fn part_one(filename: &str) {
-    println!("Part one: {}", get_height(filename, 2022));
+    match get_height(filename, "shapes.txt", 7, 2022) {
+        Ok(height) => println!("Part one: {}", height),
+        Err(e) => println!("Part one: {}", e),
+    }
 }
 
 fn part_two(filename: &str) {
-    let start = get_height(filename, 290);
-    let repeating_height = get_height(filename, 1995) - start;
-    let remainder = get_height(filename, 1585) - start;
-    println!("Part two: {}", start + 586510263 * repeating_height + remainder);
+    match get_height(filename, "shapes.txt", 7, 1_000_000_000_000) {
+        Ok(height) => println!("Part two: {}", height),
+        Err(e) => println!("Part two: {}", e),
+    }
 }
+
 fn main() {
     part_one("input.txt");
     part_two("input.txt");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shapes() {
+        let shapes = parse_shapes("shapes.txt").unwrap();
+        assert_eq!(shapes.len(), 5);
+        assert_eq!(
+            shapes[1],
+            Shape {
+                rows: vec![0b010, 0b111, 0b010],
+                width: 3,
+            }
+        );
+        assert_eq!(
+            shapes[2],
+            Shape {
+                rows: vec![0b111, 0b100, 0b100],
+                width: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_height() {
+        assert_eq!(get_height("test.txt", "shapes.txt", 7, 2022), Ok(3068));
+        assert_eq!(
+            get_height("test.txt", "shapes.txt", 7, 1_000_000_000_000),
+            Ok(1514285714288)
+        );
+    }
+
+    #[test]
+    fn test_no_fast_forward() {
+        let jets = parse_input("test.txt");
+        let shapes = parse_shapes("shapes.txt").unwrap();
+        let mut rockfall = RockFall::new(7, &shapes, &jets).unwrap();
+        for _ in 0..2022 {
+            rockfall.drop_rock();
+        }
+        assert_eq!(rockfall.height(), 3068);
+    }
+
+    #[test]
+    fn test_shape_errors() {
+        let wide = vec!["#".repeat(MAX_WIDTH + 1)];
+        assert_eq!(
+            Shape::from_ascii(&wide),
+            Err("Shape line ################# is wider than 16 columns".to_string())
+        );
+        assert_eq!(
+            Shape::from_ascii(&["#x".to_string()]),
+            Err("Unexpected character x in shape".to_string())
+        );
+        let shapes = [Shape::from_ascii(&["######".to_string()]).unwrap()];
+        assert!(RockFall::new(7, &shapes, &[Jet::Left]).is_err());
+        assert!(RockFall::new(17, &shapes, &[Jet::Left]).is_err());
+    }
+
+    #[test]
+    fn test_surface() {
+        let jets = [Jet::Left];
+        let shapes = [Shape::from_ascii(&["#".to_string()]).unwrap()];
+        let mut rockfall = RockFall::new(4, &shapes, &jets).unwrap();
+        // a shaft down the left side under an overhang on the right
+        rockfall.rows = vec![0b1110, 0b1110, 0b0000, 0b1000];
+        assert_eq!(rockfall.surface(), vec![0b0111, 0b1111, 0b0001, 0b0001]);
+    }
+
+    #[test]
+    fn test_tall_shapes() {
+        // a bar far taller than the rows a fixed window would compare
+        let bar: Vec<String> = (0..40).map(|_| "#".to_string()).collect();
+        let shapes = [
+            Shape::from_ascii(&bar).unwrap(),
+            Shape::from_ascii(&["###".to_string()]).unwrap(),
+        ];
+        let jets = parse_input("test.txt");
+        let mut rockfall = RockFall::new(7, &shapes, &jets).unwrap();
+        for _ in 0..5000 {
+            rockfall.drop_rock();
+        }
+        let skipping = RockFall::new(7, &shapes, &jets).unwrap();
+        assert_eq!(skipping.height_after(5000), rockfall.height());
+    }
+}
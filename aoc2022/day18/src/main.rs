@@ -1,4 +1,7 @@
-use std::collections::{HashMap, HashSet};
+mod voxel;
+
+use voxel::*;
+
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::ops::RangeInclusive;
@@ -9,7 +12,7 @@ use std::ops::RangeInclusive;
 /// (x+1, y+1, z), (x+1, y, z+1),
 /// (x, y+1, z+1), (x+1, y+1, z+1)
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct Cube([u8; 3]);
+struct Cube([i16; 3]);
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 enum Axis {
@@ -25,12 +28,12 @@ enum Axis {
 /// side to make a unique identification.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 struct Side {
-    anchor: [u8; 3],
+    anchor: [i16; 3],
     axis: Axis,
 }
 
 impl Cube {
-    fn new(x: u8, y: u8, z: u8) -> Self {
+    fn new(x: i16, y: i16, z: i16) -> Self {
         Self([x, y, z])
     }
 
@@ -74,8 +77,8 @@ fn parse_input(filename: &str) -> Vec<Cube> {
             break;
         }
         let coordinate: Vec<_> = line
-            .split(",")
-            .filter_map(|coord| u8::from_str_radix(coord.trim_end(), 10).ok())
+            .split(',')
+            .filter_map(|coord| coord.trim_end().parse::<i16>().ok())
             .collect();
         cubes.push(Cube([coordinate[0], coordinate[1], coordinate[2]]));
         line.clear();
@@ -83,13 +86,14 @@ fn parse_input(filename: &str) -> Vec<Cube> {
     cubes
 }
 
+#[derive(Debug, Clone)]
 struct BoundingCube {
-    min_x: u8,
-    min_y: u8,
-    min_z: u8,
-    max_x: u8,
-    max_y: u8,
-    max_z: u8,
+    min_x: i16,
+    min_y: i16,
+    min_z: i16,
+    max_x: i16,
+    max_y: i16,
+    max_z: i16,
 }
 
 impl BoundingCube {
@@ -102,26 +106,38 @@ impl BoundingCube {
             && *z <= self.max_z
     }
 
-    fn x_bounds(&self) -> RangeInclusive<u8> {
+    /// Grow the bounds by one in every direction
+    fn padded(&self) -> Self {
+        Self {
+            min_x: self.min_x - 1,
+            min_y: self.min_y - 1,
+            min_z: self.min_z - 1,
+            max_x: self.max_x + 1,
+            max_y: self.max_y + 1,
+            max_z: self.max_z + 1,
+        }
+    }
+
+    fn x_bounds(&self) -> RangeInclusive<i16> {
         self.min_x..=self.max_x
     }
 
-    fn y_bounds(&self) -> RangeInclusive<u8> {
+    fn y_bounds(&self) -> RangeInclusive<i16> {
         self.min_y..=self.max_y
     }
 
-    fn z_bounds(&self) -> RangeInclusive<u8> {
+    fn z_bounds(&self) -> RangeInclusive<i16> {
         self.min_z..=self.max_z
     }
 }
 
 fn compute_bounding_cube(cubes: &[Cube]) -> BoundingCube {
-    let mut min_x = u8::MAX;
-    let mut min_y = u8::MAX;
-    let mut min_z = u8::MAX;
-    let mut max_x = 0u8;
-    let mut max_y = 0u8;
-    let mut max_z = 0u8;
+    let mut min_x = i16::MAX;
+    let mut min_y = i16::MAX;
+    let mut min_z = i16::MAX;
+    let mut max_x = i16::MIN;
+    let mut max_y = i16::MIN;
+    let mut max_z = i16::MIN;
     for cube in cubes {
         let [x, y, z] = cube.0;
         min_x = std::cmp::min(min_x, x);
@@ -141,80 +157,44 @@ fn compute_bounding_cube(cubes: &[Cube]) -> BoundingCube {
     }
 }
 
-type AirPocket = HashSet<Cube>;
-
-fn air_pocket(cube: Cube, bounding_cube: &BoundingCube, cubes: &HashSet<Cube>) -> Option<AirPocket> {
-    if cubes.contains(&cube) {
-        return None;
-    }
+fn part_one(filename: &str) {
+    let voxels = Voxels::new(parse_input(filename));
+    println!("Surface area: {}", voxels.surface_area());
+}
 
-    let mut stack = vec![cube.clone()];
-    let mut air_pocket = HashSet::from([cube]);
-    while let Some(Cube([x, y, z])) = stack.pop() {
-        if x as u64 * y as u64 * z as u64 == 0 {
-            return None;
-        }
-        for neighbor in &[
-            Cube::new(x - 1, y, z),
-            Cube::new(x + 1, y, z),
-            Cube::new(x, y - 1, z),
-            Cube::new(x, y + 1, z),
-            Cube::new(x, y, z - 1),
-            Cube::new(x, y, z + 1),
-        ] {
-            if !bounding_cube.contains(neighbor) {
-                return None;
-            }
-            if !cubes.contains(&neighbor) && !air_pocket.contains(&neighbor) {
-                air_pocket.insert(neighbor.clone());
-                stack.push(neighbor.clone());
-            }
-        }
-    }
-    Some(air_pocket)
+fn part_two(filename: &str) {
+    let voxels = Voxels::new(parse_input(filename));
+    println!("External surface area: {}", voxels.exterior_area());
 }
 
-fn part_one(filename: &str) {
-    let cubes = parse_input(filename);
-    let bounding_cube = compute_bounding_cube(&cubes);
-    let mut out_sides = HashMap::new();
-    for cube in &cubes {
-        for side in cube.sides() {
-            out_sides
-                .entry(side)
-                .and_modify(|counter| *counter += 1)
-                .or_insert(1);
-        }
+/// Summarize the droplet and export its exterior as an OBJ mesh
+fn report(filename: &str, obj_file: &str) {
+    let voxels = Voxels::new(parse_input(filename));
+    for (ix, component) in voxels.components().iter().enumerate() {
+        println!(
+            "Component {}: volume {}, surface area {}, external surface area {}",
+            ix,
+            component.volume(),
+            component.surface_area,
+            voxels.exterior_area_of(component),
+        );
     }
-    out_sides.retain(|_, v| *v == 1);
-    println!("Surface area: {}", out_sides.len());
-
-    let cubes: HashSet<Cube> = cubes.into_iter().collect();
-    let mut interior_cubes = HashSet::new();
-    for x in bounding_cube.x_bounds() {
-        for y in bounding_cube.y_bounds() {
-            for z in bounding_cube.z_bounds() {
-                let cube_to_check = Cube::new(x, y, z);
-                if interior_cubes.contains(&cube_to_check) {
-                    continue;
-                }
-                if let Some(pocket) = air_pocket(cube_to_check, &bounding_cube, &cubes) {
-                    for cube in pocket.into_iter() {
-                        interior_cubes.insert(cube);
-                    }
-                }
-            }
-        }
+    for (ix, pocket) in voxels.air_pockets().iter().enumerate() {
+        println!(
+            "Air pocket {}: volume {}, surface area {}",
+            ix,
+            pocket.volume(),
+            pocket.surface_area,
+        );
     }
-    for cube in interior_cubes {
-        for side in cube.sides() {
-            out_sides.remove(&side);
-        }
-    }
-    println!("External surface area: {}", out_sides.len());
-
+    std::fs::write(obj_file, voxels.to_obj()).unwrap();
+    println!("Wrote exterior mesh to {}", obj_file);
 }
 
 fn main() {
     part_one("input.txt");
+    part_two("input.txt");
+    if let Some(obj_file) = std::env::args().nth(1) {
+        report("input.txt", &obj_file);
+    }
 }
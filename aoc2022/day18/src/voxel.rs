@@ -0,0 +1,258 @@
+use super::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+/// Offsets of the six face-adjacent neighbors of a cube
+const NEIGHBORS: [[i16; 3]; 6] = [
+    [-1, 0, 0],
+    [0, -1, 0],
+    [0, 0, -1],
+    [1, 0, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+];
+
+impl Cube {
+    /// The neighbors in the same order as the sides returned
+    /// by [`Cube::sides`]
+    fn neighbors(&self) -> [Cube; 6] {
+        let [x, y, z] = self.0;
+        NEIGHBORS.map(|[dx, dy, dz]| Cube::new(x + dx, y + dy, z + dz))
+    }
+}
+
+impl Side {
+    /// The corners of the side, wound counterclockwise when
+    /// looking at it from the positive end of its axis.
+    fn corners(&self) -> [[i16; 3]; 4] {
+        let [x, y, z] = self.anchor;
+        match self.axis {
+            Axis::X => [[x, y, z], [x, y + 1, z], [x, y + 1, z + 1], [x, y, z + 1]],
+            Axis::Y => [[x, y, z], [x, y, z + 1], [x + 1, y, z + 1], [x + 1, y, z]],
+            Axis::Z => [[x, y, z], [x + 1, y, z], [x + 1, y + 1, z], [x, y + 1, z]],
+        }
+    }
+}
+
+/// A face-connected set of cubes, either lava or air
+#[derive(Debug, Clone)]
+pub struct Component {
+    pub cubes: HashSet<Cube>,
+    /// Number of sides shared with cubes outside of the component
+    pub surface_area: usize,
+}
+
+impl Component {
+    fn new(cubes: HashSet<Cube>) -> Self {
+        let surface_area = cubes
+            .iter()
+            .flat_map(|cube| cube.neighbors())
+            .filter(|neighbor| !cubes.contains(neighbor))
+            .count();
+        Self {
+            cubes,
+            surface_area,
+        }
+    }
+
+    pub fn volume(&self) -> usize {
+        self.cubes.len()
+    }
+}
+
+/// A lava droplet together with the air around it
+pub struct Voxels {
+    cubes: HashSet<Cube>,
+    /// The bounding cube of the droplet padded by one,
+    /// so that the outside air is connected
+    bounds: BoundingCube,
+    /// All air cubes in `bounds` reachable from outside
+    exterior: HashSet<Cube>,
+}
+
+impl Voxels {
+    pub fn new(cubes: Vec<Cube>) -> Self {
+        // without any lava there is nothing to bound and no air around it
+        if cubes.is_empty() {
+            return Self {
+                cubes: HashSet::new(),
+                bounds: BoundingCube {
+                    min_x: 0,
+                    min_y: 0,
+                    min_z: 0,
+                    max_x: -1,
+                    max_y: -1,
+                    max_z: -1,
+                },
+                exterior: HashSet::new(),
+            };
+        }
+        let bounds = compute_bounding_cube(&cubes).padded();
+        let cubes: HashSet<Cube> = cubes.into_iter().collect();
+        let corner = Cube::new(bounds.min_x, bounds.min_y, bounds.min_z);
+        let exterior = flood_fill(corner, |cube| {
+            bounds.contains(cube) && !cubes.contains(cube)
+        });
+        Self {
+            cubes,
+            bounds,
+            exterior,
+        }
+    }
+
+    /// Number of sides not touching another lava cube
+    pub fn surface_area(&self) -> usize {
+        self.cubes
+            .iter()
+            .flat_map(|cube| cube.neighbors())
+            .filter(|neighbor| !self.cubes.contains(neighbor))
+            .count()
+    }
+
+    /// Number of sides reachable from outside the droplet
+    pub fn exterior_area(&self) -> usize {
+        self.cubes
+            .iter()
+            .flat_map(|cube| cube.neighbors())
+            .filter(|neighbor| self.exterior.contains(neighbor))
+            .count()
+    }
+
+    /// Number of sides of a component reachable from outside the droplet
+    pub fn exterior_area_of(&self, component: &Component) -> usize {
+        component
+            .cubes
+            .iter()
+            .flat_map(|cube| cube.neighbors())
+            .filter(|neighbor| self.exterior.contains(neighbor))
+            .count()
+    }
+
+    /// The face-connected pieces of the droplet
+    pub fn components(&self) -> Vec<Component> {
+        let mut seen = HashSet::new();
+        let mut components = vec![];
+        for cube in &self.cubes {
+            if seen.contains(cube) {
+                continue;
+            }
+            let component = flood_fill(cube.clone(), |c| self.cubes.contains(c));
+            seen.extend(component.iter().cloned());
+            components.push(Component::new(component));
+        }
+        components
+    }
+
+    /// The regions of air trapped inside the droplet
+    pub fn air_pockets(&self) -> Vec<Component> {
+        let is_pocket = |cube: &Cube| {
+            self.bounds.contains(cube)
+                && !self.cubes.contains(cube)
+                && !self.exterior.contains(cube)
+        };
+        let mut seen = HashSet::new();
+        let mut pockets = vec![];
+        for x in self.bounds.x_bounds() {
+            for y in self.bounds.y_bounds() {
+                for z in self.bounds.z_bounds() {
+                    let cube = Cube::new(x, y, z);
+                    if seen.contains(&cube) || !is_pocket(&cube) {
+                        continue;
+                    }
+                    let pocket = flood_fill(cube, is_pocket);
+                    seen.extend(pocket.iter().cloned());
+                    pockets.push(Component::new(pocket));
+                }
+            }
+        }
+        pockets
+    }
+
+    /// Export the exterior surface as a Wavefront OBJ mesh with
+    /// outward facing normals.
+    pub fn to_obj(&self) -> String {
+        let mut vertices: HashMap<[i16; 3], usize> = HashMap::new();
+        let mut obj = String::from("# lava droplet exterior\n");
+        let mut faces = String::new();
+        for cube in &self.cubes {
+            for (ix, (side, neighbor)) in cube.sides().into_iter().zip(cube.neighbors()).enumerate()
+            {
+                if !self.exterior.contains(&neighbor) {
+                    continue;
+                }
+                let mut corners = side.corners();
+                // the first three sides face the negative direction
+                if ix < 3 {
+                    corners.reverse();
+                }
+                let indices = corners.map(|corner| {
+                    let next = vertices.len() + 1;
+                    *vertices.entry(corner).or_insert_with(|| {
+                        writeln!(obj, "v {} {} {}", corner[0], corner[1], corner[2]).unwrap();
+                        next
+                    })
+                });
+                writeln!(
+                    faces,
+                    "f {} {} {} {}",
+                    indices[0], indices[1], indices[2], indices[3]
+                )
+                .unwrap();
+            }
+        }
+        obj.push_str(&faces);
+        obj
+    }
+}
+
+/// Collect all cubes connected to `start` that satisfy `include`
+fn flood_fill(start: Cube, include: impl Fn(&Cube) -> bool) -> HashSet<Cube> {
+    let mut queue = VecDeque::from([start.clone()]);
+    let mut filled = HashSet::from([start]);
+    while let Some(cube) = queue.pop_front() {
+        for neighbor in cube.neighbors() {
+            if include(&neighbor) && !filled.contains(&neighbor) {
+                filled.insert(neighbor.clone());
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let voxels = Voxels::new(parse_input("test.txt"));
+        assert_eq!(voxels.surface_area(), 64);
+        assert_eq!(voxels.exterior_area(), 58);
+        let pockets = voxels.air_pockets();
+        assert_eq!(pockets.len(), 1);
+        assert_eq!(pockets[0].volume(), 1);
+        assert_eq!(pockets[0].surface_area, 6);
+        let components = voxels.components();
+        assert_eq!(components.iter().map(Component::volume).sum::<usize>(), 13);
+        assert_eq!(components.iter().map(|c| c.surface_area).sum::<usize>(), 64);
+    }
+
+    #[test]
+    fn test_single_cube_mesh() {
+        let voxels = Voxels::new(vec![Cube::new(0, 0, 0)]);
+        let obj = voxels.to_obj();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 6);
+    }
+
+    #[test]
+    fn test_empty() {
+        let voxels = Voxels::new(vec![]);
+        assert_eq!(voxels.surface_area(), 0);
+        assert_eq!(voxels.exterior_area(), 0);
+        assert!(voxels.air_pockets().is_empty());
+        assert!(voxels.components().is_empty());
+        assert_eq!(voxels.to_obj(), "# lava droplet exterior\n");
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -11,22 +11,26 @@ fn parse_input(filename: &str) -> Vec<Blueprint> {
         if length == 0 {
             break;
         }
-        let [head, rest]: [&str; 2] = line.trim().split(':')
+        let [head, rest]: [&str; 2] = line
+            .trim()
+            .split(':')
             .collect::<Vec<_>>()
             .try_into()
             .unwrap();
-        let [ore, clay, obsidian, geode]: [Vec<u8>; 4] = rest[..rest.len() - 1].split('.')
-            .map(|piece|
-                piece.split(' ')
-                    .filter_map(|word| u8::from_str_radix(word, 10).ok())
+        let [ore, clay, obsidian, geode]: [Vec<u32>; 4] = rest[..rest.len() - 1]
+            .split('.')
+            .map(|piece| {
+                piece
+                    .split(' ')
+                    .filter_map(|word| word.parse::<u32>().ok())
                     .collect::<Vec<_>>()
-            )
+            })
             .collect::<Vec<_>>()
             .try_into()
             .unwrap();
 
-        blueprints.push(Blueprint{
-            id: u8::from_str_radix(head.split(' ').last().unwrap(), 10).unwrap(),
+        blueprints.push(Blueprint {
+            id: head.split(' ').next_back().unwrap().parse().unwrap(),
             ore: ore[0],
             clay: clay[0],
             obsidian: obsidian.try_into().unwrap(),
@@ -39,149 +43,307 @@ fn parse_input(filename: &str) -> Vec<Blueprint> {
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 struct Blueprint {
-    pub id: u8,
-    pub ore: u8,
-    pub clay: u8,
-    pub obsidian: [u8; 2],
-    pub geode: [u8; 2],
+    pub id: u32,
+    pub ore: u32,
+    pub clay: u32,
+    pub obsidian: [u32; 2],
+    pub geode: [u32; 2],
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+impl Blueprint {
+    /// The cost of a robot indexed by resource
+    fn cost(&self, robot: Robot) -> [u32; 4] {
+        match robot {
+            Robot::Ore => [self.ore, 0, 0, 0],
+            Robot::Clay => [self.clay, 0, 0, 0],
+            Robot::Obsidian => [self.obsidian[0], self.obsidian[1], 0, 0],
+            Robot::Geode => [self.geode[0], 0, self.geode[1], 0],
+        }
+    }
+
+    /// Only one robot can be built per minute, so there is no use in
+    /// producing more of a resource per minute than the most expensive
+    /// robot costs. Geodes are never spent.
+    fn max_spend(&self) -> [u32; 4] {
+        [
+            [self.ore, self.clay, self.obsidian[0], self.geode[0]]
+                .into_iter()
+                .max()
+                .unwrap(),
+            self.obsidian[1],
+            self.geode[1],
+            u32::MAX,
+        ]
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum Robot {
+    Ore,
+    Clay,
+    Obsidian,
+    Geode,
+}
+
+impl Robot {
+    /// Most valuable robots first, so good plans are found early
+    const ALL: [Robot; 4] = [Robot::Geode, Robot::Obsidian, Robot::Clay, Robot::Ore];
+
+    fn ix(&self) -> usize {
+        match self {
+            Robot::Ore => 0,
+            Robot::Clay => 1,
+            Robot::Obsidian => 2,
+            Robot::Geode => 3,
+        }
+    }
+}
+
+impl Display for Robot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Robot::Ore => write!(f, "ore-collecting robot"),
+            Robot::Clay => write!(f, "clay-collecting robot"),
+            Robot::Obsidian => write!(f, "obsidian-collecting robot"),
+            Robot::Geode => write!(f, "geode-cracking robot"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 struct State {
-    ore: u8,
-    clay: u8,
-    obsidian: u8,
-    geode: u8,
-    blueprint: Blueprint,
-    ore_robot: u8,
-    clay_robot: u8,
-    obsidian_robot: u8,
-    geode_robot: u8,
-    minute: u8,
-}
-
-impl From<Blueprint> for State {
-    fn from(blueprint: Blueprint) -> Self {
-        State {
-            ore: 0,
-            clay: 0,
-            obsidian: 0,
-            geode: 0,
-            blueprint,
-            ore_robot: 1,
-            clay_robot: 0,
-            obsidian_robot: 0,
-            geode_robot: 0,
+    /// Ore, clay, obsidian and geodes collected
+    resources: [u32; 4],
+    /// Robots collecting each resource
+    robots: [u32; 4],
+    /// Minutes elapsed
+    minute: u32,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            resources: [0; 4],
+            robots: [1, 0, 0, 0],
             minute: 0,
         }
     }
 }
 
 impl State {
-    fn branch(&self) -> Vec<State> {
-        let purchases = self.purchases();
-        let mut new_state: State = (*self).clone();
-        new_state.ore += self.ore_robot;
-        new_state.clay += self.clay_robot;
-        new_state.obsidian += self.obsidian_robot;
-        new_state.geode += self.geode_robot;
-        new_state.minute += 1;
-
-        purchases.into_iter()
-            .map(|robots| {
-                let mut state = new_state.clone();
-                state.buy(robots);
-                state
-            })
-            .collect()
+    /// Wait until the robot is affordable and build it. Returns `None`
+    /// if it would not be ready before the time limit.
+    fn build(&self, robot: Robot, cost: [u32; 4], time_limit: u32) -> Option<State> {
+        let mut wait = 0;
+        for ((cost, have), robots) in cost.iter().zip(self.resources).zip(self.robots) {
+            if *cost > have {
+                if robots == 0 {
+                    return None;
+                }
+                wait = std::cmp::max(wait, (cost - have).div_ceil(robots));
+            }
+        }
+        // building takes another minute
+        let minute = self.minute + wait + 1;
+        if minute >= time_limit {
+            return None;
+        }
+        let mut next = *self;
+        for ((resource, robots), cost) in next.resources.iter_mut().zip(self.robots).zip(cost) {
+            *resource += robots * (wait + 1) - cost;
+        }
+        next.robots[robot.ix()] += 1;
+        next.minute = minute;
+        Some(next)
     }
 
-    fn purchases(&self) -> HashSet<[u8; 4]> {
-        let mut visited = HashSet::new();
-        visited.insert([0u8, 0, 0, 0]);
-        let mut purchase = [0u8, 0, 0, 0];
-        for ix in 0..4 {
-            purchase[ix] += 1;
-            if self.can_afford(&purchase) {
-                visited.insert(purchase.clone());
+    /// Geodes at the time limit if nothing else is built
+    fn geodes(&self, time_limit: u32) -> u32 {
+        self.resources[3] + self.robots[3] * (time_limit - self.minute)
+    }
+
+    /// Geodes at the time limit if ore was free and every minute a clay
+    /// robot was built, as well as an obsidian and geode robot whenever
+    /// each would be affordable on its own. This never underestimates.
+    fn upper_bound(&self, blueprint: &Blueprint, time_limit: u32) -> u32 {
+        let [_, mut clay, mut obsidian, mut geodes] = self.resources;
+        let [_, mut clay_robots, mut obsidian_robots, mut geode_robots] = self.robots;
+        for _ in self.minute..time_limit {
+            let new_obsidian_robot = clay >= blueprint.obsidian[1];
+            let new_geode_robot = obsidian >= blueprint.geode[1];
+            if new_obsidian_robot {
+                clay -= blueprint.obsidian[1];
             }
-            purchase[ix] -= 1;
+            if new_geode_robot {
+                obsidian -= blueprint.geode[1];
+            }
+            clay += clay_robots;
+            obsidian += obsidian_robots;
+            geodes += geode_robots;
+            clay_robots += 1;
+            obsidian_robots += new_obsidian_robot as u32;
+            geode_robots += new_geode_robot as u32;
         }
-        visited
+        geodes
     }
+}
 
-    fn can_afford(&self, robots: &[u8; 4]) -> bool {
-        if  robots[0] * self.blueprint.ore
-            + robots[1] * self.blueprint.clay
-            + robots[2] * self.blueprint.obsidian[0]
-            + robots[3] * self.blueprint.geode[0] > self.ore {
-            return false;
-        }
-        if robots[2] * self.blueprint.obsidian[1] > self.clay {
-            return false;
-        }
-        if robots[3] * self.blueprint.geode[1] > self.obsidian {
-            return false;
+/// A robot completed at the end of the given minute
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Build {
+    minute: u32,
+    robot: Robot,
+}
+
+/// The best build order found for a blueprint
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Plan {
+    geodes: u32,
+    builds: Vec<Build>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for build in &self.builds {
+            writeln!(f, "== Minute {} == Built {}", build.minute, build.robot)?;
         }
-        true
+        write!(f, "Geodes: {}", self.geodes)
     }
+}
 
-    fn buy(&mut self, robots: [u8; 4]) {
-        // update resources
-        self.ore -= robots[0] * self.blueprint.ore
-            + robots[1] * self.blueprint.clay
-            + robots[2] * self.blueprint.obsidian[0]
-            + robots[3] * self.blueprint.geode[0];
-        self.clay -= robots[2] * self.blueprint.obsidian[1];
-        self.obsidian -= robots[3] * self.blueprint.geode[1];
-        // update robots
-        self.ore_robot += robots[0];
-        self.clay_robot += robots[1];
-        self.obsidian_robot += robots[2];
-        self.geode_robot += robots[3];
-    }
+/// Branch on the next robot to build, skipping ahead to the minute it
+/// becomes affordable.
+struct Solver<'a> {
+    blueprint: &'a Blueprint,
+    time_limit: u32,
+    costs: [[u32; 4]; 4],
+    max_spend: [u32; 4],
+    best: Plan,
+    builds: Vec<Build>,
 }
 
-fn optimize(state: State, time_limit: u8) -> u8 {
-    let mut stack = vec![state];
-    let mut visited = HashSet::new();
-    let mut max_geodes = HashMap::new();
-    while let Some(next) = stack.pop() {
-        for branch in next.branch() {
-            if !visited.contains(&branch) && branch.minute <= time_limit {
-                max_geodes.entry(branch.minute)
-                    .and_modify(|x| *x = std::cmp::max(branch.geode, *x))
-                    .or_insert(branch.geode);
-                if branch.minute < time_limit && branch.geode == max_geodes[&branch.minute] {
-                    stack.push(branch.clone());
-                }
-                visited.insert(branch);
+impl<'a> Solver<'a> {
+    fn new(blueprint: &'a Blueprint, time_limit: u32) -> Self {
+        Self {
+            blueprint,
+            time_limit,
+            costs: [Robot::Ore, Robot::Clay, Robot::Obsidian, Robot::Geode]
+                .map(|robot| blueprint.cost(robot)),
+            max_spend: blueprint.max_spend(),
+            best: Plan {
+                geodes: 0,
+                builds: vec![],
+            },
+            builds: vec![],
+        }
+    }
 
+    fn solve(mut self) -> Plan {
+        self.branch(State::default());
+        self.best
+    }
+
+    fn branch(&mut self, state: State) {
+        let geodes = state.geodes(self.time_limit);
+        if geodes > self.best.geodes {
+            self.best = Plan {
+                geodes,
+                builds: self.builds.clone(),
+            };
+        }
+        if state.upper_bound(self.blueprint, self.time_limit) <= self.best.geodes {
+            return;
+        }
+        for robot in Robot::ALL {
+            if state.robots[robot.ix()] >= self.max_spend[robot.ix()] {
+                continue;
+            }
+            if let Some(next) = state.build(robot, self.costs[robot.ix()], self.time_limit) {
+                self.builds.push(Build {
+                    minute: next.minute,
+                    robot,
+                });
+                self.branch(next);
+                self.builds.pop();
             }
         }
     }
-    println!("{}", max_geodes[&time_limit]);
-    max_geodes[&time_limit]
+}
+
+fn optimize(blueprint: &Blueprint, time_limit: u32) -> Plan {
+    Solver::new(blueprint, time_limit).solve()
 }
 
 fn part_one(filename: &str) {
     let quality = parse_input(filename)
-        .into_iter()
-        .map(|bp| bp.id as u64 * optimize(bp.into(), 24) as u64)
+        .iter()
+        .map(|bp| bp.id as u64 * optimize(bp, 24).geodes as u64)
         .sum::<u64>();
     println!("Part one: {}", quality);
 }
 
 fn part_two(filename: &str) {
     let max = parse_input(filename)
-        .into_iter()
+        .iter()
         .take(3)
-        .map(|bp| optimize(bp.into(), 32) as u64)
+        .map(|bp| optimize(bp, 32).geodes as u64)
         .product::<u64>();
     println!("Part two: {}", max)
 }
 
+/// Print the best build order for the first blueprint
+fn timeline(filename: &str, time_limit: u32) {
+    let blueprint = &parse_input(filename)[0];
+    println!(
+        "Blueprint {}:\n{}",
+        blueprint.id,
+        optimize(blueprint, time_limit)
+    );
+}
+
 fn main() {
     part_one("input.txt");
     part_two("input.txt");
+    timeline("input.txt", 24);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimize() {
+        let blueprints = parse_input("test.txt");
+        assert_eq!(optimize(&blueprints[0], 24).geodes, 9);
+        assert_eq!(optimize(&blueprints[1], 24).geodes, 12);
+        assert_eq!(optimize(&blueprints[0], 32).geodes, 56);
+        assert_eq!(optimize(&blueprints[1], 32).geodes, 62);
+    }
+
+    #[test]
+    fn test_plan_is_valid() {
+        let blueprint = &parse_input("test.txt")[0];
+        let plan = optimize(blueprint, 24);
+        // replay the plan minute by minute
+        let mut state = State::default();
+        let mut builds = plan.builds.iter().peekable();
+        for minute in 1..=24 {
+            let mut built = None;
+            if let Some(build) = builds.next_if(|b| b.minute == minute) {
+                let cost = blueprint.cost(build.robot);
+                for (resource, cost) in state.resources.iter_mut().zip(cost) {
+                    *resource = resource.checked_sub(cost).unwrap();
+                }
+                built = Some(build.robot);
+            }
+            for ix in 0..4 {
+                state.resources[ix] += state.robots[ix];
+            }
+            if let Some(robot) = built {
+                state.robots[robot.ix()] += 1;
+            }
+        }
+        assert!(builds.next().is_none());
+        assert_eq!(state.resources[3], plan.geodes);
+    }
 }
@@ -1,78 +1,136 @@
-use std::collections::HashSet;
+mod treap;
+
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use treap::OrderList;
 
-fn parse_input(filename: &str) -> Vec<(u32, i64)> {
+fn parse_input(filename: &str) -> Vec<i64> {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
     let mut numbers = vec![];
-    let mut line_number = 0;
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
             break;
         }
-        numbers.push((line_number, i64::from_str_radix(&line.trim(), 10).unwrap()));
+        numbers.push(line.trim().parse::<i64>().unwrap());
         line.clear();
-        line_number += 1;
     }
     numbers
 }
 
-fn mix(numbers: &[(u32, i64)], times: u8) -> Vec<(u32, i64)>{
-    let length = numbers.len() as i64;
-    let mut decrypted = numbers.to_owned();
-    for _ in 0..times {
-        for shift in numbers {
-            let (ix, _) = decrypted
-                .iter()
-                .enumerate()
-                .find(|(_, val)| **val == *shift)
-                .unwrap();
+/// Settings of the decryption routine
+#[derive(Debug, Clone)]
+struct Mixer {
+    pub key: i64,
+    pub rounds: usize,
+    /// Positions after the zero that are summed up
+    pub offsets: Vec<usize>,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self {
+            key: 1,
+            rounds: 1,
+            offsets: vec![1000, 2000, 3000],
+        }
+    }
+}
 
-            let next_ix = (shift.1 + ix as i64).rem_euclid(length - 1) as usize;
-            decrypted.remove(ix);
-            decrypted.insert(next_ix, *shift);
+impl Mixer {
+    /// Move every number forward or backward in the circular list by
+    /// its value, in the original order, for each round.
+    fn mix(&self, numbers: &[i64]) -> Vec<i64> {
+        let numbers: Vec<i64> = numbers.iter().map(|x| x * self.key).collect();
+        let length = numbers.len() as i64;
+        if length < 2 {
+            return numbers;
+        }
+        let mut list = OrderList::new(numbers.len());
+        for _ in 0..self.rounds {
+            for (item, shift) in numbers.iter().enumerate() {
+                let ix = list.position(item) as i64;
+                let next_ix = (ix + shift).rem_euclid(length - 1) as usize;
+                list.move_to(item, next_ix);
+            }
         }
+        list.to_vec()
+            .into_iter()
+            .map(|item| numbers[item])
+            .collect()
+    }
+
+    fn grove_coordinates(&self, numbers: &[i64]) -> i64 {
+        let decrypted = self.mix(numbers);
+        let length = decrypted.len();
+        let zero = decrypted.iter().position(|x| *x == 0).unwrap();
+        self.offsets
+            .iter()
+            .map(|offset| decrypted[(zero + offset) % length])
+            .sum()
     }
-    decrypted
 }
 
 fn part_one(filename: &str) {
     let numbers = parse_input(filename);
-    let decrypted = mix(&numbers, 1);
-    let length = numbers.len();
-    let (ix, _) = decrypted
-        .iter()
-        .enumerate()
-        .find(|(_, val)| val.1 == 0)
-        .unwrap();
-    let first = (ix + 1000).rem_euclid(length as usize);
-    let second = (ix + 2000).rem_euclid(length as usize);
-    let third = (ix + 3000).rem_euclid(length as usize);
-    println!("{}", decrypted[first].1 + decrypted[second].1 + decrypted[third].1);
+    println!("{}", Mixer::default().grove_coordinates(&numbers));
 }
 
 fn part_two(filename: &str) {
-
-    let numbers = parse_input(filename)
-        .into_iter()
-        .map(|(l, x)| (l, x * 811589153))
-        .collect::<Vec<_>>();
-    let decrypted = mix(&numbers, 10);
-    let length = numbers.len();
-    let (ix, _) = decrypted
-        .iter()
-        .enumerate()
-        .find(|(_, val)| val.1 == 0)
-        .unwrap();
-    let first = (ix + 1000).rem_euclid(length as usize);
-    let second = (ix + 2000).rem_euclid(length as usize);
-    let third = (ix + 3000).rem_euclid(length as usize);
-    println!("{}", decrypted[first].1 + decrypted[second].1 + decrypted[third].1);
+    let numbers = parse_input(filename);
+    let mixer = Mixer {
+        key: 811589153,
+        rounds: 10,
+        ..Default::default()
+    };
+    println!("{}", mixer.grove_coordinates(&numbers));
 }
 
 fn main() {
-   part_one("input.txt");
+    part_one("input.txt");
     part_two("input.txt");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rotate so that the list starts with zero
+    fn from_zero(numbers: Vec<i64>) -> Vec<i64> {
+        let zero = numbers.iter().position(|x| *x == 0).unwrap();
+        numbers[zero..]
+            .iter()
+            .chain(&numbers[..zero])
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_mix() {
+        let numbers = parse_input("test.txt");
+        assert_eq!(
+            from_zero(Mixer::default().mix(&numbers)),
+            vec![0, 3, -2, 1, 2, -3, 4]
+        );
+        assert_eq!(Mixer::default().grove_coordinates(&numbers), 3);
+        let mixer = Mixer {
+            key: 811589153,
+            rounds: 10,
+            ..Default::default()
+        };
+        assert_eq!(mixer.grove_coordinates(&numbers), 1623178306);
+    }
+
+    #[test]
+    fn test_order_list() {
+        let mut list = OrderList::new(100);
+        assert_eq!(list.to_vec(), (0..100).collect::<Vec<_>>());
+        list.move_to(3, 50);
+        list.move_to(99, 0);
+        assert_eq!(list.position(3), 51);
+        assert_eq!(list.position(99), 0);
+        assert_eq!(list.position(0), 1);
+        assert_eq!(list.len(), 100);
+    }
+}
@@ -0,0 +1,151 @@
+/// Marks a missing child or parent
+const NIL: usize = usize::MAX;
+
+/// An implicit treap holding the items `0..n` in some order. It supports
+/// finding the position of an item and moving an item to a new
+/// position in O(log n) expected time.
+pub struct OrderList {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    priority: Vec<u64>,
+    root: usize,
+}
+
+impl OrderList {
+    /// A list containing `0..n` in increasing order
+    pub fn new(n: usize) -> Self {
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let priority = (0..n)
+            .map(|_| {
+                // xorshift
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                seed
+            })
+            .collect();
+        let mut list = Self {
+            left: vec![NIL; n],
+            right: vec![NIL; n],
+            parent: vec![NIL; n],
+            size: vec![1; n],
+            priority,
+            root: NIL,
+        };
+        for item in 0..n {
+            list.root = list.merge(list.root, item);
+        }
+        list
+    }
+
+    pub fn len(&self) -> usize {
+        self.size(self.root)
+    }
+
+    fn size(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.size[node]
+        }
+    }
+
+    /// Recompute the size of a node and repair the parent
+    /// pointers of its children.
+    fn update(&mut self, node: usize) {
+        let (left, right) = (self.left[node], self.right[node]);
+        self.size[node] = 1 + self.size(left) + self.size(right);
+        if left != NIL {
+            self.parent[left] = node;
+        }
+        if right != NIL {
+            self.parent[right] = node;
+        }
+    }
+
+    /// Split a tree into its first `k` items and the rest
+    fn split(&mut self, node: usize, k: usize) -> (usize, usize) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+        self.parent[node] = NIL;
+        let left_size = self.size(self.left[node]);
+        if k <= left_size {
+            let (first, rest) = self.split(self.left[node], k);
+            self.left[node] = rest;
+            self.update(node);
+            (first, node)
+        } else {
+            let (first, rest) = self.split(self.right[node], k - left_size - 1);
+            self.right[node] = first;
+            self.update(node);
+            (node, rest)
+        }
+    }
+
+    /// Concatenate two trees
+    fn merge(&mut self, first: usize, second: usize) -> usize {
+        if first == NIL {
+            return second;
+        }
+        if second == NIL {
+            return first;
+        }
+        if self.priority[first] > self.priority[second] {
+            self.right[first] = self.merge(self.right[first], second);
+            self.parent[first] = NIL;
+            self.update(first);
+            first
+        } else {
+            self.left[second] = self.merge(first, self.left[second]);
+            self.parent[second] = NIL;
+            self.update(second);
+            second
+        }
+    }
+
+    /// The current position of an item
+    pub fn position(&self, item: usize) -> usize {
+        let mut position = self.size(self.left[item]);
+        let mut node = item;
+        while self.parent[node] != NIL {
+            let parent = self.parent[node];
+            if self.right[parent] == node {
+                position += self.size(self.left[parent]) + 1;
+            }
+            node = parent;
+        }
+        position
+    }
+
+    /// Take an item out of the list and reinsert it so that
+    /// it ends up at position `to`
+    pub fn move_to(&mut self, item: usize, to: usize) {
+        let from = self.position(item);
+        let (before, rest) = self.split(self.root, from);
+        let (_, after) = self.split(rest, 1);
+        let rest = self.merge(before, after);
+        let (before, after) = self.split(rest, to);
+        let before = self.merge(before, item);
+        self.root = self.merge(before, after);
+    }
+
+    /// The items in list order
+    pub fn to_vec(&self) -> Vec<usize> {
+        let mut items = Vec::with_capacity(self.len());
+        let mut stack = vec![];
+        let mut node = self.root;
+        while node != NIL || !stack.is_empty() {
+            while node != NIL {
+                stack.push(node);
+                node = self.left[node];
+            }
+            node = stack.pop().unwrap();
+            items.push(node);
+            node = self.right[node];
+        }
+        items
+    }
+}
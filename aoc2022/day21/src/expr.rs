@@ -0,0 +1,174 @@
+use super::*;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    fn apply(&self, first: SignedRational, second: SignedRational) -> Option<SignedRational> {
+        match self {
+            Op::Add => Some(first + second),
+            Op::Sub => Some(first - second),
+            Op::Mul => Some(first * second),
+            Op::Div if second == 0.into() => None,
+            Op::Div => Some(first / second),
+        }
+    }
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Op::Add => write!(f, "+"),
+            Op::Sub => write!(f, "-"),
+            Op::Mul => write!(f, "*"),
+            Op::Div => write!(f, "/"),
+        }
+    }
+}
+
+/// An arithmetic expression in at most one unknown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Const(SignedRational),
+    Unknown(String),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Build the expression a monkey yells. The monkey named
+    /// `unknown` is replaced by a variable.
+    pub fn from_jobs(name: &str, jobs: &HashMap<String, Job>, unknown: Option<&str>) -> Self {
+        if Some(name) == unknown {
+            return Expr::Unknown(name.to_string());
+        }
+        let (op, [first, second]) = match &jobs[name] {
+            Job::Yell(value) => return Expr::Const(*value),
+            Job::Sum(deps) => (Op::Add, deps),
+            Job::Minus(deps) => (Op::Sub, deps),
+            Job::Mult(deps) => (Op::Mul, deps),
+            Job::Div(deps) => (Op::Div, deps),
+        };
+        Expr::Binary(
+            op,
+            Box::new(Expr::from_jobs(first, jobs, unknown)),
+            Box::new(Expr::from_jobs(second, jobs, unknown)),
+        )
+    }
+
+    /// Evaluate every subexpression not depending on the unknown
+    pub fn fold(self) -> Self {
+        match self {
+            Expr::Binary(op, first, second) => {
+                let first = first.fold();
+                let second = second.fold();
+                match (&first, &second) {
+                    (Expr::Const(a), Expr::Const(b)) => match op.apply(*a, *b) {
+                        Some(value) => Expr::Const(value),
+                        None => Expr::Binary(op, Box::new(first), Box::new(second)),
+                    },
+                    _ => Expr::Binary(op, Box::new(first), Box::new(second)),
+                }
+            }
+            expr => expr,
+        }
+    }
+
+    /// The value of an expression without unknowns
+    pub fn value(&self) -> Option<SignedRational> {
+        match self.clone().fold() {
+            Expr::Const(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Unknown(name) => write!(f, "{}", name),
+            Expr::Binary(op, first, second) => {
+                for (ix, operand) in [first, second].into_iter().enumerate() {
+                    if ix == 1 {
+                        write!(f, " {} ", op)?;
+                    }
+                    if let Expr::Binary(..) = **operand {
+                        write!(f, "({})", operand)?;
+                    } else {
+                        write!(f, "{}", operand)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Two expressions that should be equal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Equation {
+    pub lhs: Expr,
+    pub rhs: Expr,
+}
+
+impl Equation {
+    pub fn fold(self) -> Self {
+        Self {
+            lhs: self.lhs.fold(),
+            rhs: self.rhs.fold(),
+        }
+    }
+
+    /// Find the value of the unknown by undoing the operations applied
+    /// to it one at a time. Returns `None` unless the unknown appears
+    /// exactly once and has a unique solution.
+    pub fn solve(&self) -> Option<SignedRational> {
+        let folded = self.clone().fold();
+        let (mut expr, mut target) = match (&folded.lhs, &folded.rhs) {
+            (expr, Expr::Const(target)) | (Expr::Const(target), expr) => (expr, *target),
+            _ => return None,
+        };
+        loop {
+            let (op, first, second) = match expr {
+                Expr::Unknown(_) => return Some(target),
+                Expr::Const(_) => return None,
+                Expr::Binary(op, first, second) => (op, first, second),
+            };
+            target = match (&**first, &**second) {
+                // unknown on the left: first `op` b = target
+                (first, Expr::Const(b)) => {
+                    expr = first;
+                    match op {
+                        Op::Add => target - b,
+                        Op::Sub => target + b,
+                        Op::Mul => Op::Div.apply(target, *b)?,
+                        Op::Div => target * b,
+                    }
+                }
+                // unknown on the right: a `op` second = target
+                (Expr::Const(a), second) => {
+                    expr = second;
+                    match op {
+                        Op::Add => target - a,
+                        Op::Sub => a - target,
+                        Op::Mul => Op::Div.apply(target, *a)?,
+                        Op::Div => Op::Div.apply(*a, target)?,
+                    }
+                }
+                _ => return None,
+            };
+        }
+    }
+}
+
+impl Display for Equation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.lhs, self.rhs)
+    }
+}
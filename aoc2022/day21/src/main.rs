@@ -1,329 +1,127 @@
-use std::collections::{HashMap, HashSet};
+mod expr;
+
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use expr::*;
 use num_rational::Ratio;
-type SignedRational = Ratio<i64>;
+type SignedRational = Ratio<i128>;
 
 #[derive(Debug, Clone)]
 enum Job {
     Yell(SignedRational),
-    Sum([JobStatus; 2]),
-    Minus([JobStatus; 2]),
-    Mult([JobStatus; 2]),
-    Div([JobStatus; 2]),
-    Eq([JobStatus; 2]),
-}
-
-#[derive(Debug, Clone)]
-enum JobStatus {
-    Pending,
-    WaitingOn(String),
-    Finished(SignedRational),
-}
-
-#[derive(Debug, Clone)]
-struct Monkey {
-    name: String,
-    job: Job,
-    status: JobStatus,
+    Sum([String; 2]),
+    Minus([String; 2]),
+    Mult([String; 2]),
+    Div([String; 2]),
 }
 
-fn parse_input(filename: &str) -> Vec<Monkey> {
+fn parse_input(filename: &str) -> HashMap<String, Job> {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
-    let mut monkeys = vec![];
+    let mut monkeys = HashMap::new();
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
             break;
         }
         let [name, op]: [&str; 2] = line.split(':').collect::<Vec<&str>>().try_into().unwrap();
-        let (job, status) = if op.contains('+') {
-            (
-                Job::Sum(
-                    op.split('+')
-                        .map(|x| JobStatus::WaitingOn(String::from(x.trim())))
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap(),
-                ),
-                JobStatus::Pending,
-            )
+        let deps = |sep: char| -> [String; 2] {
+            op.split(sep)
+                .map(|x| String::from(x.trim()))
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap()
+        };
+        let job = if op.contains('+') {
+            Job::Sum(deps('+'))
         } else if op.contains('*') {
-            (
-                Job::Mult(
-                    op.split('*')
-                        .map(|x| JobStatus::WaitingOn(String::from(x.trim())))
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap(),
-                ),
-                JobStatus::Pending,
-            )
+            Job::Mult(deps('*'))
         } else if op.contains('-') {
-            (
-                Job::Minus(
-                    op.split('-')
-                        .map(|x| JobStatus::WaitingOn(String::from(x.trim())))
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap(),
-                ),
-                JobStatus::Pending,
-            )
+            Job::Minus(deps('-'))
         } else if op.contains('/') {
-            (
-                Job::Div(
-                    op.split('/')
-                        .map(|x| JobStatus::WaitingOn(String::from(x.trim())))
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap(),
-                ),
-                JobStatus::Pending,
-            )
+            Job::Div(deps('/'))
         } else {
-            (
-                Job::Yell(i64::from_str_radix(op.trim(), 10).unwrap().into()),
-                JobStatus::Finished(i64::from_str_radix(op.trim(), 10).unwrap().into()),
-            )
+            Job::Yell(op.trim().parse::<i128>().unwrap().into())
         };
-        monkeys.push(Monkey {
-            name: name.to_string(),
-            job,
-            status,
-        });
+        monkeys.insert(name.to_string(), job);
         line.clear();
     }
     monkeys
 }
 
-fn update_status(
-    first: &mut JobStatus,
-    second: &mut JobStatus,
-    monkey_map: &HashMap<String, Monkey>,
-) -> [Option<SignedRational>; 2] {
-    let first_status = match first {
-        JobStatus::WaitingOn(monkey) => {
-            if let Some(JobStatus::Finished(res)) = monkey_map.get(monkey).map(|m| &m.status) {
-                *first = JobStatus::Finished(*res);
-                Some(*res)
-            } else {
-                None
-            }
-        }
-        JobStatus::Finished(res) => Some(*res),
-        _ => None,
+/// The root monkey compares the numbers of the two monkeys it waits on
+/// and the human's number is unknown.
+fn equation(monkeys: &HashMap<String, Job>) -> Equation {
+    let [first, second] = match &monkeys["root"] {
+        Job::Sum(deps) | Job::Minus(deps) | Job::Mult(deps) | Job::Div(deps) => deps,
+        Job::Yell(_) => panic!("The root monkey must wait on two monkeys"),
     };
-    let second_status = match second {
-        JobStatus::WaitingOn(monkey) => {
-            if let Some(JobStatus::Finished(res)) = monkey_map.get(monkey).map(|m| &m.status) {
-                *second = JobStatus::Finished(*res);
-                Some(*res)
-            } else {
-                None
-            }
-        }
-        JobStatus::Finished(res) => Some(*res),
-        _ => None,
-    };
-    [first_status, second_status]
+    Equation {
+        lhs: Expr::from_jobs(first, monkeys, Some("humn")),
+        rhs: Expr::from_jobs(second, monkeys, Some("humn")),
+    }
 }
 
 fn part_one(filename: &str) {
-    let mut monkeys = parse_input(filename);
-    let mut monkey_map: HashMap<String, Monkey> = monkeys
-        .iter()
-        .cloned()
-        .map(|monk| (monk.name.clone(), monk))
-        .collect();
-    reduce_expr(&mut monkeys, &mut monkey_map);
-    if let JobStatus::Finished(result) = monkey_map["root"].status {
-        println!("Part one: {}", result);
-    } else {
-        println!("Oops");
+    let monkeys = parse_input(filename);
+    match Expr::from_jobs("root", &monkeys, None).value() {
+        Some(result) => println!("Part one: {}", result),
+        None => println!("Oops"),
     }
 }
 
 fn part_two(filename: &str) {
-    let mut monkeys = parse_input(filename);
-
-    let root = monkeys
-        .iter_mut()
-        .find(|monkey| monkey.name.as_str() == "root")
-        .unwrap();
-    root.job = Job::Eq(match root.job.clone() {
-        Job::Sum(deps) => deps,
-        Job::Mult(deps) => deps,
-        Job::Minus(deps) => deps,
-        Job::Div(deps) => deps,
-        Job::Eq(deps) => deps,
-        Job::Yell(_) => unreachable!(),
-    });
-    let (ix, _) = monkeys
-        .iter()
-        .enumerate()
-        .find(|monkey| monkey.1.name.as_str() == "humn")
-        .unwrap();
-    let mut human = monkeys.remove(ix);
-    let mut monkey_map: HashMap<String, Monkey> = monkeys
-        .iter()
-        .cloned()
-        .map(|monk| (monk.name.clone(), monk))
-        .collect();
-
-    reduce_expr(&mut monkeys, &mut monkey_map);
-    human.job = Job::Yell(0.into());
-    human.status = JobStatus::Finished(0.into());
-    monkeys.push(human.clone());
-    monkey_map.insert(human.name.clone(), human);
-    let factors = get_factorized_const(monkeys.clone(), monkey_map.clone());
-    let mut tried = HashSet::new();
-    for set in pow_set(&factors) {
-        let val: i64 = set.into_iter().product::<u64>() as i64;
-        if !tried.insert(val) {
-            continue;
-        }
-        let mut test_monkeys = monkeys.clone();
-        let mut test_map = monkey_map.clone();
-        test_monkeys.last_mut().map(|human| {
-            human.job = Job::Yell(val.into());
-            human.status = JobStatus::Finished(val.into());
-            test_map
-                .get_mut("humn")
-                .map(|h| h.job = Job::Yell(val.into()));
-            test_map
-                .get_mut("humn")
-                .map(|h| h.status = JobStatus::Finished(val.into()));
-        });
-        reduce_expr(&mut test_monkeys, &mut test_map);
-        let const_term = if let JobStatus::Finished(res) = test_map["root"].status {
-            *res.reduced().numer() as u64
-        } else {
-            unreachable!()
-        };
-        if const_term == 1 {
-            println!("Part two: {}", val);
-            return;
-        }
+    let monkeys = parse_input(filename);
+    let equation = equation(&monkeys).fold();
+    println!("{}", equation);
+    match equation.solve() {
+        Some(result) => println!("Part two: {}", result),
+        None => println!("Oops"),
     }
 }
 
-fn get_factorized_const(
-    mut monkeys: Vec<Monkey>,
-    mut monkey_map: HashMap<String, Monkey>,
-) -> Vec<u64> {
-    reduce_expr(&mut monkeys, &mut monkey_map);
-    let const_term = if let Job::Eq([JobStatus::Finished(first), JobStatus::Finished(second)]) =
-        monkey_map["root"].job
-    {
-        if first > second {
-            first - second
-        } else {
-            second - first
-        }
-    } else {
-        unreachable!()
-    };
-    let const_term = *const_term.reduced().numer() as u64 * 784;
-    primes::factors(const_term)
-}
-
-fn pow_set<T>(s: &[T]) -> Vec<Vec<T>>
-where
-    T: Copy,
-{
-    (0..2usize.pow(s.len() as u32))
-        .map(|i| {
-            s.iter()
-                .enumerate()
-                .filter(|&(t, _)| (i >> t) % 2 == 1)
-                .map(|(_, element)| *element)
-                .collect()
-        })
-        .collect()
+fn main() {
+    part_one("input.txt");
+    part_two("input.txt");
 }
 
-fn reduce_expr(monkeys: &mut Vec<Monkey>, monkey_map: &mut HashMap<String, Monkey>) {
-    let mut completed = HashSet::new();
-    let mut finished;
-    let mut reduced;
-    loop {
-        finished = true;
-        reduced = true;
-        for monkey in monkeys.iter_mut() {
-            if let JobStatus::Pending = monkey.status {
-                finished = false;
-                match &mut monkey.job {
-                    Job::Sum([first, second]) => {
-                        if let [Some(res1), Some(res2)] = update_status(first, second, &monkey_map)
-                        {
-                            monkey.status = JobStatus::Finished(res1 + res2);
-                            reduced = false;
-                        }
-                    }
-                    Job::Minus([first, second]) => {
-                        if let [Some(res1), Some(res2)] = update_status(first, second, &monkey_map)
-                        {
-                            monkey.status = JobStatus::Finished(res1 - res2);
-                            reduced = false;
-                        }
-                    }
-                    Job::Mult([first, second]) => {
-                        if let [Some(res1), Some(res2)] = update_status(first, second, &monkey_map)
-                        {
-                            monkey.status = JobStatus::Finished(res1 * res2);
-                            reduced = false;
-                        }
-                    }
-                    Job::Div([first, second]) => {
-                        if let [Some(res1), Some(res2)] = update_status(first, second, &monkey_map)
-                        {
-                            monkey.status = JobStatus::Finished(res1 / res2);
-                            reduced = false;
-                        }
-                    }
-                    Job::Eq([first, second]) => {
-                        if let [Some(res1), Some(res2)] = update_status(first, second, &monkey_map)
-                        {
-                            monkey.status = JobStatus::Finished(
-                                if res1.reduced() == res2.reduced() {
-                                    1
-                                } else {
-                                    0
-                                }
-                                .into(),
-                            );
-                            reduced = false;
-                        }
-                    }
-                    _ => {}
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                if let JobStatus::Finished(_) = monkey.status {
-                    completed.insert(monkey.name.clone());
-                }
-                let mapped = monkey_map.get_mut(&monkey.name).unwrap();
-                mapped.status = monkey.status.clone();
-                mapped.job = monkey.job.clone();
-            }
-            if let JobStatus::Finished(_) = monkey.status {
-                completed.insert(monkey.name.clone());
-            }
-        }
-        if finished || reduced {
-            break;
-        }
-    }
-    if !finished {
-        completed.remove("root");
-        monkeys.retain(|monkey| !completed.contains(&monkey.name));
-        monkey_map.retain(|monkey, _| !completed.contains(monkey));
+    #[test]
+    fn test_solve() {
+        let monkeys = parse_input("test.txt");
+        assert_eq!(
+            Expr::from_jobs("root", &monkeys, None).value(),
+            Some(152.into())
+        );
+        let equation = equation(&monkeys).fold();
+        assert_eq!(equation.to_string(), "(4 + (2 * (humn - 3))) / 4 = 150");
+        assert_eq!(equation.solve(), Some(301.into()));
     }
-}
 
-fn main() {
-    part_one("input.txt");
-    part_two("input.txt");
+    #[test]
+    fn test_no_unique_solution() {
+        let zero = |unknown: &str| Equation {
+            lhs: Expr::Binary(
+                Op::Mul,
+                Box::new(Expr::Const(0.into())),
+                Box::new(Expr::Unknown(unknown.to_string())),
+            ),
+            rhs: Expr::Const(0.into()),
+        };
+        assert_eq!(zero("x").solve(), None);
+        let twice = Equation {
+            lhs: Expr::Binary(
+                Op::Add,
+                Box::new(Expr::Unknown("x".to_string())),
+                Box::new(Expr::Unknown("x".to_string())),
+            ),
+            rhs: Expr::Const(2.into()),
+        };
+        assert_eq!(twice.solve(), None);
+    }
 }
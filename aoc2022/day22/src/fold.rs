@@ -0,0 +1,187 @@
+use super::*;
+
+type Vec3 = [i64; 3];
+
+fn neg(v: Vec3) -> Vec3 {
+    v.map(|x| -x)
+}
+
+/// Where a face ends up after folding the net into a cube: its
+/// outward normal and the directions its columns and rows increase in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Orientation {
+    normal: Vec3,
+    right: Vec3,
+    down: Vec3,
+}
+
+impl Orientation {
+    fn direction(&self, heading: &Heading) -> Vec3 {
+        match heading {
+            Heading::East => self.right,
+            Heading::West => neg(self.right),
+            Heading::South => self.down,
+            Heading::North => neg(self.down),
+        }
+    }
+
+    /// The orientation of the face next to this one in the net
+    /// once the shared edge is folded.
+    fn fold(&self, heading: &Heading) -> Self {
+        match heading {
+            Heading::East => Self {
+                normal: self.right,
+                right: neg(self.normal),
+                down: self.down,
+            },
+            Heading::West => Self {
+                normal: neg(self.right),
+                right: self.normal,
+                down: self.down,
+            },
+            Heading::South => Self {
+                normal: self.down,
+                right: self.right,
+                down: neg(self.normal),
+            },
+            Heading::North => Self {
+                normal: neg(self.down),
+                right: self.right,
+                down: self.normal,
+            },
+        }
+    }
+}
+
+const HEADINGS: [Heading; 4] = [Heading::North, Heading::South, Heading::East, Heading::West];
+
+/// The six faces of a board folded into a cube
+#[derive(Debug)]
+pub struct CubeNet {
+    /// The side length of a face
+    pub size: u64,
+    /// Top left corner of each face on the board
+    faces: Vec<(u64, u64)>,
+    orientations: Vec<Orientation>,
+}
+
+impl CubeNet {
+    /// Find the faces of any of the cube nets drawn on the board and
+    /// fold them up.
+    pub fn fold(board: &Board) -> Self {
+        let size = ((board.len() / 6) as f64).sqrt() as u64;
+        assert_eq!(
+            6 * size * size,
+            board.len() as u64,
+            "The board does not have the area of a cube"
+        );
+        let mut faces: Vec<(u64, u64)> = board
+            .keys()
+            .filter(|(row, col)| row % size == 0 && col % size == 0)
+            .cloned()
+            .collect();
+        faces.sort();
+        assert_eq!(faces.len(), 6, "The board is not a cube net");
+
+        // walk over the net folding each face onto its neighbor
+        let mut orientations = vec![None; 6];
+        orientations[0] = Some(Orientation {
+            normal: [0, 0, 1],
+            right: [1, 0, 0],
+            down: [0, 1, 0],
+        });
+        let mut stack = vec![0];
+        while let Some(ix) = stack.pop() {
+            let (row, col) = faces[ix];
+            let orientation = orientations[ix].unwrap();
+            for heading in HEADINGS {
+                let neighbor = match heading {
+                    Heading::North => row.checked_sub(size).map(|row| (row, col)),
+                    Heading::South => Some((row + size, col)),
+                    Heading::East => Some((row, col + size)),
+                    Heading::West => col.checked_sub(size).map(|col| (row, col)),
+                };
+                let Some(next) = neighbor.and_then(|n| faces.iter().position(|f| *f == n)) else {
+                    continue;
+                };
+                if orientations[next].is_none() {
+                    orientations[next] = Some(orientation.fold(&heading));
+                    stack.push(next);
+                }
+            }
+        }
+        let orientations: Vec<Orientation> = orientations
+            .into_iter()
+            .map(|o| o.expect("The faces of the net are not connected"))
+            .collect();
+        for (ix, orientation) in orientations.iter().enumerate() {
+            assert!(
+                orientations[..ix]
+                    .iter()
+                    .all(|other| other.normal != orientation.normal),
+                "The net folds onto itself"
+            );
+        }
+        Self {
+            size,
+            faces,
+            orientations,
+        }
+    }
+
+    fn face_of(&self, (row, col): (u64, u64)) -> usize {
+        self.faces
+            .iter()
+            .position(|(r, c)| {
+                (*r..r + self.size).contains(&row) && (*c..c + self.size).contains(&col)
+            })
+            .unwrap()
+    }
+
+    /// Where you end up after walking off the edge of a face
+    pub fn wrap(&self, position: (u64, u64), heading: &Heading) -> ((u64, u64), Heading) {
+        let from = self.face_of(position);
+        let (row, col) = (
+            position.0 - self.faces[from].0,
+            position.1 - self.faces[from].1,
+        );
+        let orientation = &self.orientations[from];
+        let exit = orientation.direction(heading);
+        let to = self
+            .orientations
+            .iter()
+            .position(|o| o.normal == exit)
+            .unwrap();
+        let next = &self.orientations[to];
+        // the shared edge lies in the direction of the old normal
+        let entry = HEADINGS
+            .into_iter()
+            .find(|h| next.direction(h) == orientation.normal)
+            .unwrap();
+
+        // keep the position along the shared edge
+        let (along, offset) = match heading {
+            Heading::East | Heading::West => (orientation.down, row),
+            Heading::North | Heading::South => (orientation.right, col),
+        };
+        let next_along = match entry {
+            Heading::East | Heading::West => next.down,
+            Heading::North | Heading::South => next.right,
+        };
+        let offset = if along == next_along {
+            offset
+        } else {
+            self.size - 1 - offset
+        };
+        let (row, col) = match entry {
+            Heading::North => (0, offset),
+            Heading::South => (self.size - 1, offset),
+            Heading::West => (offset, 0),
+            Heading::East => (offset, self.size - 1),
+        };
+        (
+            (self.faces[to].0 + row, self.faces[to].1 + col),
+            entry.opposite(),
+        )
+    }
+}
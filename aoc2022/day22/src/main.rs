@@ -1,10 +1,10 @@
-mod parse_cube;
+mod fold;
 mod part2;
 
 pub use part2::*;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::Read;
 
 #[derive(Debug, Clone)]
 enum WrapsTo {
@@ -33,7 +33,7 @@ impl Tile {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Heading {
     North,
     South,
@@ -123,38 +123,40 @@ impl You {
     }
 }
 
-fn parse_input(file_prefix: &str) -> (Vec<Instruction>, Board) {
-    // parse the directions
-    let mut file = File::open(format!("{}_dirs.txt", file_prefix)).unwrap();
-    let mut directions = String::new();
-    _ = file.read_to_string(&mut directions).unwrap();
-    let directions = parse_directions(directions);
+/// The board is followed by a blank line and the directions
+fn parse_input(filename: &str) -> (Vec<Instruction>, Board) {
+    let mut file = File::open(filename).unwrap();
+    let mut contents = String::new();
+    _ = file.read_to_string(&mut contents).unwrap();
+    let (map, directions) = contents.split_once("\n\n").unwrap();
+    let directions = parse_directions(directions.trim().to_string());
 
     // parse the board
     let mut board = HashMap::<(u64, u64), Tile>::new();
-    let file = File::open(format!("{}.txt", file_prefix)).unwrap();
-    let mut reader = BufReader::new(file);
-    let mut line = String::new();
-    let mut row = 0;
-    while let Ok(length) = reader.read_line(&mut line) {
-        if length == 0 {
-            break;
-        }
+    for (row, line) in map.lines().enumerate() {
         board.extend(line.chars().enumerate().filter_map(|(col, c)| match c {
-            '.' => Some(((row, col as u64), Tile::Open)),
-            '#' => Some(((row, col as u64), Tile::Closed)),
+            '.' => Some(((row as u64, col as u64), Tile::Open)),
+            '#' => Some(((row as u64, col as u64), Tile::Closed)),
             _ => None,
         }));
-        row += 1;
-        line.clear();
     }
     (directions, construct_edges(board))
 }
 
+/// The leftmost open tile of the top row
+fn start(board: &Board) -> (u64, u64) {
+    *board
+        .iter()
+        .filter(|((row, _), tile)| *row == 0 && tile.is_open())
+        .map(|(pos, _)| pos)
+        .min_by_key(|x| x.1)
+        .unwrap()
+}
+
 fn parse_directions(dirs: String) -> Vec<Instruction> {
     let mut moves: Vec<Instruction> = dirs
-        .split(|c| c == 'L' || c == 'R')
-        .map(|amt| Instruction::Move(u64::from_str_radix(amt.trim(), 10).unwrap()))
+        .split(['L', 'R'])
+        .map(|amt| Instruction::Move(amt.trim().parse::<u64>().unwrap()))
         .collect();
     let mut turns: Vec<Instruction> = dirs
         .chars()
@@ -173,7 +175,7 @@ fn parse_directions(dirs: String) -> Vec<Instruction> {
         };
         let mut instructions: Vec<_> = turns
             .into_iter()
-            .zip(moves.into_iter())
+            .zip(moves)
             .flat_map(|pair| [pair.0, pair.1].into_iter())
             .collect();
         if let Some(tail) = optional_tail {
@@ -188,7 +190,7 @@ fn parse_directions(dirs: String) -> Vec<Instruction> {
         };
         let mut instructions: Vec<_> = moves
             .into_iter()
-            .zip(turns.into_iter())
+            .zip(turns)
             .flat_map(|pair| [pair.0, pair.1].into_iter())
             .collect();
         if let Some(tail) = optional_tail {
@@ -201,11 +203,7 @@ fn parse_directions(dirs: String) -> Vec<Instruction> {
 fn construct_edges(mut board: Board) -> Board {
     let mut updates = HashMap::<(u64, u64), Tile>::new();
     for ((row, col), tile) in &board {
-        let open = if let Tile::Closed = tile {
-            false
-        } else {
-            true
-        };
+        let open = !matches!(tile, Tile::Closed);
         let north = if *row == 0 || !board.contains_key(&(row - 1, *col)) {
             board.keys()
                 .filter(|(_, c) | c == col)
@@ -257,14 +255,10 @@ fn construct_edges(mut board: Board) -> Board {
     board
 }
 
-fn part_one(file_prefix: &str) {
-    let (directions, board) = parse_input(file_prefix);
-    let start = *board.keys()
-        .filter(|(row, _)| *row == 0)
-        .min_by_key(|x| x.1)
-        .unwrap();
+fn part_one(filename: &str) {
+    let (directions, board) = parse_input(filename);
     let mut you = You {
-        position: start,
+        position: start(&board),
         heading: Heading::East,
     };
     for instruction in directions {
@@ -275,6 +269,6 @@ fn part_one(file_prefix: &str) {
 }
 
 fn main() {
-    part_one("input");
-    part_2();
+    part_one("input.txt");
+    part_two("input.txt");
 }
@@ -0,0 +1,297 @@
+use std::fmt::{Display, Formatter};
+
+type Pos = (i64, i64);
+
+/// A proposal: an elf wants to move by `step` if all the
+/// offsets in `clear` are free.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub clear: Vec<Pos>,
+    pub step: Pos,
+}
+
+/// What happens when several elves propose the same tile
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// None of them move
+    Cancel,
+    /// The first elf to propose it moves, the others stay
+    FirstWins,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Proposals in the order they are considered
+    pub proposals: Vec<Rule>,
+    /// How far the proposals are rotated left after each round
+    pub rotation: usize,
+    pub conflict: Conflict,
+    /// Elves without any neighbors do not propose anything
+    pub stay_if_alone: bool,
+}
+
+impl Rules {
+    /// The furthest any rule looks or moves
+    fn reach(&self) -> i64 {
+        self.proposals
+            .iter()
+            .flat_map(|rule| rule.clear.iter().chain([&rule.step]))
+            .map(|(row, col)| std::cmp::max(row.abs(), col.abs()))
+            .max()
+            .unwrap_or_default()
+            .max(1)
+    }
+}
+
+/// A dense bitset over a rectangle of the plane that grows as needed
+#[derive(Debug, Clone)]
+pub struct Grid {
+    /// The coordinates of the top left cell
+    origin: Pos,
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl Grid {
+    fn new(origin: Pos, width: usize, height: usize) -> Self {
+        let words_per_row = width.div_ceil(64);
+        Self {
+            origin,
+            width,
+            height,
+            words_per_row,
+            bits: vec![0; words_per_row * height],
+        }
+    }
+
+    /// The word and bit of a position if it is on the grid
+    fn index(&self, (row, col): Pos) -> Option<(usize, u64)> {
+        let row = usize::try_from(row - self.origin.0).ok()?;
+        let col = usize::try_from(col - self.origin.1).ok()?;
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        Some((row * self.words_per_row + col / 64, 1 << (col % 64)))
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.index(pos)
+            .map(|(word, bit)| self.bits[word] & bit != 0)
+            .unwrap_or(false)
+    }
+
+    fn insert(&mut self, pos: Pos) {
+        let (word, bit) = self.index(pos).expect("Position is not on the grid");
+        self.bits[word] |= bit;
+    }
+
+    fn remove(&mut self, pos: Pos) {
+        if let Some((word, bit)) = self.index(pos) {
+            self.bits[word] &= !bit;
+        }
+    }
+
+    /// Check that every position within `margin` of the bounds is on the grid
+    fn covers(&self, bounds: &Bounds, margin: i64) -> bool {
+        bounds.min_row - margin >= self.origin.0
+            && bounds.min_col - margin >= self.origin.1
+            && bounds.max_row + margin < self.origin.0 + self.height as i64
+            && bounds.max_col + margin < self.origin.1 + self.width as i64
+    }
+}
+
+/// The smallest rectangle containing all elves
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_row: i64,
+    pub max_row: i64,
+    pub min_col: i64,
+    pub max_col: i64,
+}
+
+impl Bounds {
+    /// With no elves the bounds are empty, with `max < min` and no area
+    fn of(elves: &[Pos]) -> Self {
+        if elves.is_empty() {
+            return Bounds {
+                min_row: 0,
+                max_row: -1,
+                min_col: 0,
+                max_col: -1,
+            };
+        }
+        let mut bounds = Bounds {
+            min_row: i64::MAX,
+            max_row: i64::MIN,
+            min_col: i64::MAX,
+            max_col: i64::MIN,
+        };
+        for (row, col) in elves {
+            bounds.min_row = std::cmp::min(bounds.min_row, *row);
+            bounds.max_row = std::cmp::max(bounds.max_row, *row);
+            bounds.min_col = std::cmp::min(bounds.min_col, *col);
+            bounds.max_col = std::cmp::max(bounds.max_col, *col);
+        }
+        bounds
+    }
+
+    pub fn area(&self) -> usize {
+        ((1 + self.max_row - self.min_row) * (1 + self.max_col - self.min_col)) as usize
+    }
+}
+
+/// What happened in a single round
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RoundStats {
+    /// Rounds are counted from one
+    pub round: usize,
+    pub moved: usize,
+    pub bounds: Bounds,
+    /// Empty tiles in the bounding rectangle
+    pub empty: usize,
+}
+
+/// Elves spreading out according to a set of rules. Iterating yields
+/// one [`RoundStats`] per round until a round in which no elf moves.
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    elves: Vec<Pos>,
+    grid: Grid,
+    rules: Rules,
+    round: usize,
+    done: bool,
+}
+
+impl Automaton {
+    pub fn new(elves: Vec<Pos>, rules: Rules) -> Self {
+        let bounds = Bounds::of(&elves);
+        let mut automaton = Self {
+            elves,
+            grid: Grid::new((bounds.min_row, bounds.min_col), 0, 0),
+            rules,
+            round: 0,
+            done: false,
+        };
+        automaton.grow(&bounds);
+        automaton
+    }
+
+    /// Reallocate the grid with room to spare around the elves
+    fn grow(&mut self, bounds: &Bounds) {
+        let pad = self.rules.reach()
+            + std::cmp::max(
+                bounds.max_row - bounds.min_row,
+                bounds.max_col - bounds.min_col,
+            ) / 2;
+        self.grid = Grid::new(
+            (bounds.min_row - pad, bounds.min_col - pad),
+            (1 + bounds.max_col - bounds.min_col + 2 * pad) as usize,
+            (1 + bounds.max_row - bounds.min_row + 2 * pad) as usize,
+        );
+        for elf in &self.elves {
+            self.grid.insert(*elf);
+        }
+    }
+
+    fn is_alone(&self, (row, col): Pos) -> bool {
+        (-1..=1).all(|dr| {
+            (-1..=1).all(|dc| (dr == 0 && dc == 0) || !self.grid.contains((row + dr, col + dc)))
+        })
+    }
+
+    /// The tile an elf proposes to move to, if any
+    fn propose(&self, elf: Pos) -> Option<Pos> {
+        if self.rules.stay_if_alone && self.is_alone(elf) {
+            return None;
+        }
+        let count = self.rules.proposals.len();
+        (0..count)
+            .map(|ix| &self.rules.proposals[(ix + self.round * self.rules.rotation) % count])
+            .find(|rule| {
+                rule.clear
+                    .iter()
+                    .all(|(dr, dc)| !self.grid.contains((elf.0 + dr, elf.1 + dc)))
+            })
+            .map(|rule| (elf.0 + rule.step.0, elf.1 + rule.step.1))
+    }
+
+    /// Run a single round and return the number of elves that moved
+    fn step(&mut self) -> usize {
+        let bounds = Bounds::of(&self.elves);
+        if !self.grid.covers(&bounds, self.rules.reach()) {
+            self.grow(&bounds);
+        }
+        let proposals: Vec<Option<Pos>> = self.elves.iter().map(|elf| self.propose(*elf)).collect();
+
+        // count the proposals for each tile on a grid of the same shape
+        let mut counts = vec![0u8; self.grid.width * self.grid.height];
+        let count_ix = |grid: &Grid, (row, col): Pos| {
+            (row - grid.origin.0) as usize * grid.width + (col - grid.origin.1) as usize
+        };
+        for target in proposals.iter().flatten() {
+            let ix = count_ix(&self.grid, *target);
+            counts[ix] = counts[ix].saturating_add(1);
+        }
+
+        let mut moved = 0;
+        for (elf, target) in self.elves.iter_mut().zip(proposals) {
+            let Some(target) = target else {
+                continue;
+            };
+            let ix = count_ix(&self.grid, target);
+            // a rule may step outside the tiles it checks, so the target
+            // can still hold an elf that stays put
+            let allowed = !self.grid.contains(target)
+                && match self.rules.conflict {
+                    Conflict::Cancel => counts[ix] == 1,
+                    Conflict::FirstWins => true,
+                };
+            if allowed {
+                self.grid.remove(*elf);
+                self.grid.insert(target);
+                *elf = target;
+                moved += 1;
+            }
+        }
+        self.round += 1;
+        moved
+    }
+}
+
+impl Iterator for Automaton {
+    type Item = RoundStats;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let moved = self.step();
+        self.done = moved == 0;
+        let bounds = Bounds::of(&self.elves);
+        Some(RoundStats {
+            round: self.round,
+            moved,
+            bounds,
+            empty: bounds.area() - self.elves.len(),
+        })
+    }
+}
+
+impl Display for Automaton {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let bounds = Bounds::of(&self.elves);
+        for row in bounds.min_row..=bounds.max_row {
+            for col in bounds.min_col..=bounds.max_col {
+                if self.grid.contains((row, col)) {
+                    write!(f, "#")?;
+                } else {
+                    write!(f, ".")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
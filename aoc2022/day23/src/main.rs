@@ -1,4 +1,6 @@
-use std::collections::{HashMap, HashSet};
+mod automaton;
+
+use automaton::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -10,12 +12,11 @@ enum Direction {
     East,
 }
 
-
-fn parse_input(filename: &str) -> HashSet<(i64, i64)> {
-    let mut file = File::open(filename).unwrap();
+fn parse_input(filename: &str) -> Vec<(i64, i64)> {
+    let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
-    let mut field = HashSet::new();
+    let mut field = vec![];
     let mut row = 0i64;
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
@@ -23,7 +24,7 @@ fn parse_input(filename: &str) -> HashSet<(i64, i64)> {
         }
         for (col, c) in line.trim().chars().enumerate() {
             if c == '#' {
-                field.insert((row, col as i64));
+                field.push((row, col as i64));
             }
         }
         row += 1;
@@ -32,150 +33,123 @@ fn parse_input(filename: &str) -> HashSet<(i64, i64)> {
     field
 }
 
-fn neighbors((row, col): (i64, i64)) -> [(i64, i64); 8] {
-    [
-        (row - 1, col - 1),
-        (row, col - 1),
-        (row + 1, col - 1),
-        (row - 1, col + 1),
-        (row, col + 1),
-        (row + 1, col + 1),
-        (row - 1, col),
-        (row + 1, col),
-    ]
+impl Direction {
+    /// Move one step in this direction if the three tiles
+    /// facing it are empty
+    fn rule(&self) -> Rule {
+        let (step, clear) = match self {
+            Direction::North => ((-1, 0), [(-1, -1), (-1, 0), (-1, 1)]),
+            Direction::South => ((1, 0), [(1, -1), (1, 0), (1, 1)]),
+            Direction::West => ((0, -1), [(-1, -1), (0, -1), (1, -1)]),
+            Direction::East => ((0, 1), [(-1, 1), (0, 1), (1, 1)]),
+        };
+        Rule {
+            clear: clear.to_vec(),
+            step,
+        }
+    }
 }
 
-fn consider_moves(
-    consider: &[Direction],
-    elves: &HashSet<(i64, i64)>,
-) -> HashMap<(i64, i64), (i64, i64)> {
-    let mut proposals = HashMap::<(i64, i64), Vec::<(i64, i64)>>::new();
-    for elf in elves {
-        if neighbors(*elf)
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            proposals: [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East,
+            ]
             .iter()
-            .all(|pos| !elves.contains(pos)) {
-            continue;
-        }
-        for dir in consider {
-            match dir {
-                Direction::North => if !elves.contains(&(elf.0 - 1, elf.1 - 1))
-                    && !elves.contains(&(elf.0 - 1, elf.1))
-                    && !elves.contains(&(elf.0 - 1, elf.1 + 1))
-                {
-                    proposals.entry((elf.0 - 1, elf.1))
-                        .and_modify(|x| x.push(*elf))
-                        .or_insert(vec![*elf]);
-                    break;
-                }
-                Direction::South => if !elves.contains(&(elf.0 + 1, elf.1 - 1))
-                    && !elves.contains(&(elf.0 + 1, elf.1))
-                    && !elves.contains(&(elf.0 + 1, elf.1 + 1))
-                {
-                    proposals.entry( (elf.0 + 1, elf.1))
-                        .and_modify(|x| x.push(*elf))
-                        .or_insert(vec![*elf]);
-                    break;
-                }
-                Direction::West => if !elves.contains(&(elf.0 - 1, elf.1 - 1))
-                    && !elves.contains(&(elf.0, elf.1 - 1))
-                    && !elves.contains(&(elf.0 + 1, elf.1 - 1))
-                {
-                    proposals.entry((elf.0, elf.1 - 1))
-                        .and_modify(|x| x.push(*elf))
-                        .or_insert(vec![*elf]);
-                    break;
-                }
-                Direction::East => if !elves.contains(&(elf.0 - 1, elf.1 + 1))
-                    && !elves.contains(&(elf.0, elf.1 + 1))
-                    && !elves.contains(&(elf.0 + 1, elf.1 + 1))
-                {
-                    proposals.entry((elf.0, elf.1 + 1))
-                        .and_modify(|x| x.push(*elf))
-                        .or_insert(vec![*elf]);
-                    break;
-                }
-            }
+            .map(Direction::rule)
+            .collect(),
+            rotation: 1,
+            conflict: Conflict::Cancel,
+            stay_if_alone: true,
         }
     }
-    proposals.retain(|_, v| v.len() == 1);
-    proposals.into_iter().map(|(k, v)| (v[0], k)).collect()
 }
 
-fn display(elves: &HashSet<(i64, i64)>) {
-    let mut min_row = i64::MAX;
-    let mut min_col = i64::MAX;
-    let mut max_row = i64::MIN;
-    let mut max_col = i64::MIN;
-    for (row, col) in elves {
-        if *row < min_row {
-            min_row = *row;
-        }
-        if *row > max_row {
-            max_row = *row;
-        }
-        if *col < min_col {
-            min_col = *col;
-        }
-        if *col > max_col {
-            max_col = *col;
-        }
-    }
-    for row in min_row..=max_row {
-        let mut line = String::new();
-        for col in min_col..=max_col {
-            if elves.contains(&(row, col)) {
-                line.push('#');
-            } else {
-                line.push('.');
-            }
-        }
-        println!("{}", line);
-    }
-    println!("============");
+fn part_one(filename: &str) {
+    let elves = parse_input(filename);
+    let mut automaton = Automaton::new(elves, Rules::default());
+    let tenth = automaton.nth(9).unwrap();
+    println!("Part one: {}", tenth.empty);
 }
 
-fn run(filename: &str) {
-    let mut elves = parse_input(filename);
-    let mut consider = vec![Direction::North, Direction::South, Direction::West, Direction::East];
-    let mut round = 0u64;
-    loop {
-        let moves = consider_moves(&consider, &elves);
-        if moves.is_empty() {
-            break;
-        }
-        round += 1;
-        consider.rotate_left(1);
-        for (elf, new_pos) in moves {
-            elves.remove(&elf);
-            elves.insert(new_pos);
-        }
-        if round == 10 {
-            let mut min_row = i64::MAX;
-            let mut min_col = i64::MAX;
-            let mut max_row = i64::MIN;
-            let mut max_col = i64::MIN;
-            for (row, col) in &elves {
-                if *row < min_row {
-                    min_row = *row;
-                }
-                if *row > max_row {
-                    max_row = *row;
-                }
-                if *col < min_col {
-                    min_col = *col;
-                }
-                if *col > max_col {
-                    max_col = *col;
-                }
-            }
-            let area = (1 + max_row - min_row) * (1 + max_col - min_col);
-            println!("Part one: {}", area as usize - elves.len());
-        }
-    }
+fn part_two(filename: &str) {
+    let elves = parse_input(filename);
+    let last = Automaton::new(elves, Rules::default()).last().unwrap();
+    println!("Part two: {}", last.round);
+}
 
-    println!("Part two: {}", round + 1);
+/// The same puzzle if the first elf to propose a tile gets to move there
+fn first_wins(filename: &str) {
+    let elves = parse_input(filename);
+    let rules = Rules {
+        conflict: Conflict::FirstWins,
+        ..Default::default()
+    };
+    let last = Automaton::new(elves, rules).last().unwrap();
+    println!("First proposal wins: {} rounds", last.round);
 }
 
 fn main() {
-    run("input.txt");
+    part_one("input.txt");
+    part_two("input.txt");
+    first_wins("input.txt");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let elves = parse_input("test.txt");
+        let mut automaton = Automaton::new(elves.clone(), Rules::default());
+        assert_eq!(automaton.nth(9).unwrap().empty, 110);
+        assert_eq!(automaton.last().unwrap().round, 20);
+        let last = Automaton::new(elves, Rules::default()).last().unwrap();
+        assert_eq!(last.round, 20);
+        assert_eq!(last.moved, 0);
+    }
+
+    #[test]
+    fn test_small_example() {
+        let elves = vec![(1, 2), (1, 3), (2, 2), (4, 2), (4, 3)];
+        let mut automaton = Automaton::new(elves, Rules::default());
+        let stats: Vec<usize> = automaton.by_ref().map(|stats| stats.moved).collect();
+        assert_eq!(stats, vec![3, 5, 3, 0]);
+        assert_eq!(
+            automaton.to_string(),
+            "..#..\n....#\n#....\n....#\n.....\n..#..\n"
+        );
+    }
+
+    #[test]
+    fn test_no_elves() {
+        let mut automaton = Automaton::new(vec![], Rules::default());
+        let stats = automaton.next().unwrap();
+        assert_eq!((stats.round, stats.moved, stats.empty), (1, 0, 0));
+        assert_eq!(automaton.next(), None);
+        assert_eq!(automaton.to_string(), "");
+    }
+
+    #[test]
+    fn test_occupied_target() {
+        // nothing is checked before stepping, so the left elf tries to
+        // move onto the right one before it has moved away and stays
+        let rules = Rules {
+            proposals: vec![Rule {
+                clear: vec![],
+                step: (0, 1),
+            }],
+            rotation: 0,
+            conflict: Conflict::Cancel,
+            stay_if_alone: false,
+        };
+        let mut automaton = Automaton::new(vec![(0, 0), (0, 1)], rules);
+        assert_eq!(automaton.next().unwrap().moved, 1);
+        assert_eq!(automaton.to_string(), "#.#\n");
+    }
 }
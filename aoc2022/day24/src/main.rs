@@ -1,177 +1,237 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
+type Pos = (usize, usize);
+
+/// A fixed size set of small integers
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn new(size: usize) -> Self {
+        Self(vec![0; size.div_ceil(64)])
+    }
+
+    fn insert(&mut self, ix: usize) {
+        self.0[ix / 64] |= 1 << (ix % 64);
+    }
+
+    fn contains(&self, ix: usize) -> bool {
+        self.0[ix / 64] & (1 << (ix % 64)) != 0
+    }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct Blizzard {
-    pos: (u64, u64),
-    dir: Direction,
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+/// The blizzards only depend on the time modulo the width (for those
+/// moving horizontally) and the height (for those moving vertically)
+/// of the inner valley, so they are stored as bitsets per row and column
+/// for each of those times.
+#[derive(Debug, Clone)]
 struct Valley {
-    max_row: u64,
-    max_col: u64,
-    blizzards: Vec<Blizzard>,
-    you: (u64, u64),
-    mins: u64,
+    /// Rows and columns including the walls
+    rows: usize,
+    cols: usize,
+    start: Pos,
+    end: Pos,
+    /// `horizontal[row][t % width]` are the columns of the inner valley
+    /// covered by blizzards moving east or west
+    horizontal: Vec<Vec<BitSet>>,
+    /// `vertical[col][t % height]` are the rows of the inner valley
+    /// covered by blizzards moving north or south
+    vertical: Vec<Vec<BitSet>>,
 }
 
 impl Valley {
-    fn step(&mut self) {
-        for blizzard in self.blizzards.iter_mut() {
-            let pos = match blizzard.dir {
-                Direction::North => if blizzard.pos.0 == 1 {
-                    (self.max_row - 1, blizzard.pos.1)
-                } else {
-                    (blizzard.pos.0 - 1, blizzard.pos.1)
-                }
-                Direction::South => if blizzard.pos.0 == self.max_row - 1 {
-                    (1, blizzard.pos.1)
-                } else {
-                    (blizzard.pos.0 + 1, blizzard.pos.1)
-                }
-                Direction::East => if blizzard.pos.1 == self.max_col - 1 {
-                    (blizzard.pos.0, 1)
-                } else {
-                    (blizzard.pos.0, blizzard.pos.1 + 1)
-                }
-                Direction::West => if blizzard.pos.1 == 1 {
-                    (blizzard.pos.0, self.max_col - 1)
-                } else {
-                    (blizzard.pos.0, blizzard.pos.1 - 1)
-                }
-            };
-            blizzard.pos = pos;
-        }
+    fn width(&self) -> usize {
+        self.cols - 2
     }
 
-    fn safe(&self, you: (u64, u64)) -> bool {
-        !self.blizzards.iter().any(|b| b.pos == you)
+    fn height(&self) -> usize {
+        self.rows - 2
     }
 
-    fn neighbors(&self) -> Vec<(u64, u64)> {
-        let (row, col) = self.you;
-        let mut ngbhrs = vec![];
-        if row != 0 && row != self.max_row {
-            if row == 1 && col == 1 {
-                ngbhrs.push((0, 1));
-            }
-            if row > 1 {
-                ngbhrs.push((row - 1, col));
-            }
-            if col > 1 {
-                ngbhrs.push((row, col - 1))
-            }
-            if row < self.max_row - 1 {
-                ngbhrs.push((row + 1, col));
-            }
-            if col < self.max_col - 1 {
-                ngbhrs.push((row, col + 1));
-            }
-            if row == self.max_row - 1 && col == self.max_col - 1 {
-                ngbhrs.push((self.max_row, self.max_col - 1));
-            }
-        } else if row == 0 {
-            ngbhrs.push((1, 1));
-        } else if row == self.max_row {
-            ngbhrs.push((self.max_row - 1, self.max_col - 1));
+    /// After this many minutes the blizzards are back where they started
+    fn period(&self) -> usize {
+        self.width() * self.height() / gcd(self.width(), self.height())
+    }
+
+    fn safe(&self, (row, col): Pos, time: usize) -> bool {
+        if (row, col) == self.start || (row, col) == self.end {
+            return true;
+        }
+        if row == 0 || col == 0 || row >= self.rows - 1 || col >= self.cols - 1 {
+            return false;
         }
-        ngbhrs.push(self.you);
-        ngbhrs
+        let (row, col) = (row - 1, col - 1);
+        !self.horizontal[row][time % self.width()].contains(col)
+            && !self.vertical[col][time % self.height()].contains(row)
     }
-}
 
-fn parse_input(filename: &str) -> Valley {
-    let file = File::open(filename).unwrap();
-    let mut reader = BufReader::new(file);
-    let mut line = String::new();
-
-    let mut blizzards = vec![];
-    let mut row = 0u64;
-    let mut cols = 0u64;
-    while let Ok(length) = reader.read_line(&mut line) {
-        if length == 0 {
-            break;
+    /// Waiting or taking a step in any direction
+    fn moves(&self, (row, col): Pos) -> impl Iterator<Item = Pos> + '_ {
+        [
+            Some((row, col)),
+            row.checked_sub(1).map(|row| (row, col)),
+            Some((row + 1, col)).filter(|(row, _)| *row < self.rows),
+            col.checked_sub(1).map(|col| (row, col)),
+            Some((row, col + 1)).filter(|(_, col)| *col < self.cols),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Find the fastest way to visit the waypoints in order, leaving the
+    /// first one at `start_time`.
+    fn plan(&self, waypoints: &[Pos], start_time: usize) -> Option<Trip> {
+        let period = self.period();
+        let legs = waypoints.len().checked_sub(1)?;
+        if legs == 0 {
+            return Some(Trip {
+                start: start_time,
+                end: start_time,
+                route: waypoints.to_vec(),
+            });
         }
-        cols = line.len() as u64;
-        for (col, c) in line.chars().enumerate() {
-            match c {
-                '>' => blizzards.push(Blizzard{pos: (row, col as u64), dir: Direction::East}),
-                '^' => blizzards.push(Blizzard{pos: (row, col as u64), dir: Direction::North}),
-                '<' => blizzards.push(Blizzard{pos: (row, col as u64), dir: Direction::West}),
-                'v' => blizzards.push(Blizzard{pos: (row, col as u64), dir: Direction::South}),
-                _ => {}
+        // a state is a position, the time modulo the period and the
+        // number of waypoints reached
+        let index = |(row, col): Pos, time: usize, reached: usize| {
+            ((reached * period + time % period) * self.rows + row) * self.cols + col
+        };
+        let mut parent = vec![usize::MAX; legs * period * self.rows * self.cols];
+        let first = index(waypoints[0], start_time, 0);
+        parent[first] = first;
+        let mut queue = VecDeque::from([(waypoints[0], start_time, 0usize)]);
+        while let Some((pos, time, reached)) = queue.pop_front() {
+            let current = index(pos, time, reached);
+            for next in self.moves(pos) {
+                if !self.safe(next, time + 1) {
+                    continue;
+                }
+                let reached = reached + (next == waypoints[reached + 1]) as usize;
+                if reached == legs {
+                    let mut route = vec![next];
+                    let mut state = current;
+                    while parent[state] != state {
+                        route.push(self.decode(state));
+                        state = parent[state];
+                    }
+                    route.push(self.decode(state));
+                    route.reverse();
+                    return Some(Trip {
+                        start: start_time,
+                        end: time + 1,
+                        route,
+                    });
+                }
+                let ix = index(next, time + 1, reached);
+                if parent[ix] == usize::MAX {
+                    parent[ix] = current;
+                    queue.push_back((next, time + 1, reached));
+                }
             }
         }
-        row += 1;
-        line.clear();
+        None
     }
-    Valley {
-        max_row: row - 1,
-        max_col: cols - 1,
-        blizzards,
-        you: (0, 1),
-        mins: 0,
+
+    fn decode(&self, state: usize) -> Pos {
+        let cell = state % (self.rows * self.cols);
+        (cell / self.cols, cell % self.cols)
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-struct Vertex {
-    pos: (u64, u64),
-    time: u64
+/// The positions visited each minute from `start` to `end`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Trip {
+    start: usize,
+    end: usize,
+    route: Vec<Pos>,
 }
 
-impl<'a> From<&'a Valley> for Vertex {
-    fn from(valley: &'a Valley) -> Self {
-        Self {
-            pos: valley.you,
-            time: valley.mins,
-        }
-    }
-}
+fn parse_input(filename: &str) -> Valley {
+    let file = File::open(filename).unwrap();
+    let reader = BufReader::new(file);
+    let lines: Vec<Vec<char>> = reader
+        .lines()
+        .map(|line| line.unwrap().trim().chars().collect())
+        .filter(|line: &Vec<char>| !line.is_empty())
+        .collect();
+    let rows = lines.len();
+    let cols = lines[0].len();
+    let (width, height) = (cols - 2, rows - 2);
+    let opening = |line: &[char]| line.iter().position(|c| *c == '.').unwrap();
+    let start = (0, opening(&lines[0]));
+    let end = (rows - 1, opening(&lines[rows - 1]));
 
-fn bfs(dest: (u64, u64), valley: Valley) -> Valley {
-    let mut stack = VecDeque::from([valley]);
-    let mut visited = HashSet::<Vertex>::new();
-    while let Some(mut valley) = stack.pop_front() {
-        valley.step();
-        for neighbor in valley.neighbors() {
-            if valley.safe(neighbor) {
-                let mut next_valley = valley.clone();
-                next_valley.you = neighbor;
-                next_valley.mins += 1;
-                if neighbor == dest {
-                    return next_valley;
-                }
-                if !visited.contains(&(&next_valley).into()) {
-                    stack.push_back(next_valley.clone());
-                    visited.insert((&next_valley).into());
-                }
+    let mut horizontal = vec![vec![BitSet::new(width); width]; height];
+    let mut vertical = vec![vec![BitSet::new(height); height]; width];
+    for (row, line) in lines[1..rows - 1].iter().enumerate() {
+        for (col, c) in line[1..cols - 1].iter().enumerate() {
+            match c {
+                '>' => (0..width).for_each(|t| horizontal[row][t].insert((col + t) % width)),
+                '<' => (0..width)
+                    .for_each(|t| horizontal[row][t].insert((col + width - t % width) % width)),
+                'v' => (0..height).for_each(|t| vertical[col][t].insert((row + t) % height)),
+                '^' => (0..height)
+                    .for_each(|t| vertical[col][t].insert((row + height - t % height) % height)),
+                _ => {}
             }
         }
     }
-    Default::default()
+    Valley {
+        rows,
+        cols,
+        start,
+        end,
+        horizontal,
+        vertical,
+    }
 }
 
-
 fn run(filename: &str) {
     let valley = parse_input(filename);
-    let valley = bfs((valley.max_row, valley.max_col - 1), valley);
-    println!("Part one: {}", valley.mins);
-    let valley = bfs((0, 1), valley);
-    let valley = bfs((valley.max_row, valley.max_col - 1), valley);
-    println!("Part two: {}", valley.mins);
+    let there = valley.plan(&[valley.start, valley.end], 0).unwrap();
+    println!("Part one: {}", there.end);
+    let snacks = valley
+        .plan(&[valley.start, valley.end, valley.start, valley.end], 0)
+        .unwrap();
+    println!("Part two: {}", snacks.end);
 }
 
 fn main() {
     run("input.txt")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let valley = parse_input("test.txt");
+        assert_eq!(valley.period(), 12);
+        let there = valley.plan(&[valley.start, valley.end], 0).unwrap();
+        assert_eq!(there.end, 18);
+        assert_eq!(there.route.len(), 19);
+        let back = valley.plan(&[valley.end, valley.start], 18).unwrap();
+        assert_eq!(back.end, 41);
+        let snacks = valley
+            .plan(&[valley.start, valley.end, valley.start, valley.end], 0)
+            .unwrap();
+        assert_eq!(snacks.end, 54);
+        // every step of the route is safe and moves at most one tile
+        for (minute, pos) in snacks.route.iter().enumerate() {
+            assert!(valley.safe(*pos, minute));
+        }
+        for step in snacks.route.windows(2) {
+            assert!(valley.moves(step[0]).any(|pos| pos == step[1]));
+        }
+    }
+}
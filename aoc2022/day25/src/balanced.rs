@@ -0,0 +1,249 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::iter::Sum;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// Symbols for the negative digits, one bar for each unit
+const NEGATIVE_DIGITS: [char; 4] = ['-', '=', '≡', '≣'];
+
+/// An integer of arbitrary size written in a balanced base, where the
+/// digits run from `-(B / 2)` to `B / 2`. Negative digits are written
+/// with bars, so `=` is minus two.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BalancedBase<const B: u32> {
+    /// Least significant digit first, without trailing zeros
+    digits: Vec<i8>,
+}
+
+/// SNAFU numbers from the fuel requirements
+pub type Snafu = BalancedBase<5>;
+pub type BalancedTernary = BalancedBase<3>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDigitError(pub char);
+
+impl<const B: u32> BalancedBase<B> {
+    const HALF: i64 = {
+        assert!(
+            B % 2 == 1 && B >= 3 && B <= 9,
+            "Only odd bases from 3 to 9 are supported"
+        );
+        (B / 2) as i64
+    };
+
+    /// Build a number from digits of any size by carrying the excess. The
+    /// carry is kept in `i128` so that digits near the `i64` limits can't
+    /// overflow it.
+    fn from_raw(raw: Vec<i128>) -> Self {
+        let mut digits = Vec::with_capacity(raw.len() + 1);
+        let mut carry = 0i128;
+        let mut raw = raw.into_iter();
+        loop {
+            let value = match raw.next() {
+                Some(digit) => digit + carry,
+                None if carry != 0 => carry,
+                None => break,
+            };
+            let half = Self::HALF as i128;
+            let digit = (value + half).rem_euclid(B as i128) - half;
+            carry = (value - digit) / B as i128;
+            digits.push(digit as i8);
+        }
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self { digits }
+    }
+
+    fn symbol(digit: i8) -> char {
+        if digit < 0 {
+            NEGATIVE_DIGITS[(-digit - 1) as usize]
+        } else {
+            char::from_digit(digit as u32, 10).unwrap()
+        }
+    }
+
+    fn digit(symbol: char) -> Option<i8> {
+        let digit = match NEGATIVE_DIGITS.iter().position(|c| *c == symbol) {
+            Some(ix) => -(ix as i64) - 1,
+            None => symbol.to_digit(10)? as i64,
+        };
+        (digit.abs() <= Self::HALF).then_some(digit as i8)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The sign is the sign of the leading digit
+    pub fn signum(&self) -> Ordering {
+        self.digits
+            .last()
+            .map(|digit| digit.cmp(&0))
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Rewrite the number in another balanced base
+    pub fn convert<const C: u32>(&self) -> BalancedBase<C> {
+        let base = BalancedBase::<C>::from(B as i64);
+        self.digits
+            .iter()
+            .rev()
+            .fold(BalancedBase::<C>::default(), |acc, digit| {
+                &(&acc * &base) + &BalancedBase::<C>::from(*digit as i64)
+            })
+    }
+}
+
+impl<const B: u32> From<i64> for BalancedBase<B> {
+    fn from(value: i64) -> Self {
+        Self::from_raw(vec![value as i128])
+    }
+}
+
+impl<const B: u32> TryFrom<&BalancedBase<B>> for i64 {
+    type Error = ();
+
+    /// Partial sums can pass the `i64` limits on the way to a value that
+    /// fits, so they are accumulated in `i128`
+    fn try_from(value: &BalancedBase<B>) -> Result<Self, Self::Error> {
+        let value = value.digits.iter().rev().try_fold(0i128, |acc, digit| {
+            acc.checked_mul(B as i128)
+                .and_then(|acc| acc.checked_add(*digit as i128))
+                .ok_or(())
+        })?;
+        i64::try_from(value).map_err(|_| ())
+    }
+}
+
+impl<const B: u32> FromStr for BalancedBase<B> {
+    type Err = ParseDigitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s
+            .chars()
+            .rev()
+            .map(|c| Self::digit(c).map(i128::from).ok_or(ParseDigitError(c)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_raw(digits))
+    }
+}
+
+impl<const B: u32> Display for BalancedBase<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        for digit in self.digits.iter().rev() {
+            write!(f, "{}", Self::symbol(*digit))?;
+        }
+        Ok(())
+    }
+}
+
+impl<const B: u32> Add for &BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let length = std::cmp::max(self.digits.len(), rhs.digits.len());
+        let raw = (0..length)
+            .map(|ix| {
+                *self.digits.get(ix).unwrap_or(&0) as i128
+                    + *rhs.digits.get(ix).unwrap_or(&0) as i128
+            })
+            .collect();
+        BalancedBase::from_raw(raw)
+    }
+}
+
+impl<const B: u32> Add for BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl<const B: u32> Neg for &BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn neg(self) -> Self::Output {
+        BalancedBase {
+            digits: self.digits.iter().map(|digit| -digit).collect(),
+        }
+    }
+}
+
+impl<const B: u32> Neg for BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl<const B: u32> Sub for &BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + &(-rhs)
+    }
+}
+
+impl<const B: u32> Sub for BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl<const B: u32> Mul for &BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        if self.is_zero() || rhs.is_zero() {
+            return BalancedBase::default();
+        }
+        let mut raw = vec![0i128; self.digits.len() + rhs.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            for (j, b) in rhs.digits.iter().enumerate() {
+                raw[i + j] += *a as i128 * *b as i128;
+            }
+        }
+        BalancedBase::from_raw(raw)
+    }
+}
+
+impl<const B: u32> Mul for BalancedBase<B> {
+    type Output = BalancedBase<B>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl<const B: u32> Ord for BalancedBase<B> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self - other).signum()
+    }
+}
+
+impl<const B: u32> PartialOrd for BalancedBase<B> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const B: u32> Sum for BalancedBase<B> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, x| &acc + &x)
+    }
+}
+
+impl<'a, const B: u32> Sum<&'a BalancedBase<B>> for BalancedBase<B> {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, x| &acc + x)
+    }
+}
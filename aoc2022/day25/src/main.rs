@@ -1,98 +1,99 @@
+mod balanced;
+
+use balanced::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-fn snafu_to_base10(snafu: &str) -> u64 {
-    snafu
-        .chars()
-        .rev()
-        .enumerate()
-        .map(|(pow, coeff)| 5i64.pow(pow as u32) * match coeff {
-            '2' => 2i64,
-            '1' => 1,
-            '0' => 0,
-            '-' => -1,
-            '=' => -2,
-            _ => unreachable!()
-        })
-        .sum::<i64>() as u64
+fn parse_input(filename: &str) -> Vec<Snafu> {
+    let file = File::open(filename).unwrap();
+    let reader = BufReader::new(file);
+    reader
+        .lines()
+        .map(|line| line.unwrap())
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().parse().unwrap())
+        .collect()
 }
 
-fn base5_to_10(base5: &[u8]) -> u64 {
-    if base5.len() == 0 {
-        return 0;
-    }
-    base5
-        .into_iter()
-        .rev()
-        .enumerate()
-        .map(|(pow, coeff)| 5u64.pow(pow as u32) * (*coeff as u64))
-        .sum()
+fn part_one(filename: &str) {
+    let sum: Snafu = parse_input(filename).into_iter().sum();
+    println!("Part one: {}", sum);
+    let ternary: BalancedTernary = sum.convert();
+    println!("In balanced ternary: {}", ternary);
 }
 
-fn to_base5(mut base10: u64) -> Vec<u8> {
-    let mut power = 1;
-    let mut pow = loop {
-        if 5u64.pow(power) > base10 {
-            break power - 1;
+fn main() {
+    part_one("input.txt");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: [(i64, &str); 11] = [
+        (1, "1"),
+        (2, "2"),
+        (3, "1="),
+        (4, "1-"),
+        (5, "10"),
+        (10, "20"),
+        (15, "1=0"),
+        (20, "1-0"),
+        (2022, "1=11-2"),
+        (12345, "1-0---0"),
+        (314159265, "1121-1110-1=0"),
+    ];
+
+    #[test]
+    fn test_snafu() {
+        for (decimal, snafu) in EXAMPLES {
+            let parsed: Snafu = snafu.parse().unwrap();
+            assert_eq!(parsed, Snafu::from(decimal));
+            assert_eq!(parsed.to_string(), snafu);
+            assert_eq!(i64::try_from(&parsed), Ok(decimal));
         }
-        power += 1;
-    };
-    let mut digits = vec![];
-    while pow > 0 {
-        let place = base10 / 5u64.pow(pow);
-        base10 = base10.rem_euclid(5u64.pow(pow));
-        digits.push(place as u8);
-        pow -= 1;
+        let sum: Snafu = parse_input("test.txt").into_iter().sum();
+        assert_eq!(sum.to_string(), "2=-1=0");
+        assert_eq!(i64::try_from(&sum), Ok(4890));
+        assert_eq!(Snafu::default().to_string(), "0");
+        assert_eq!("1=3".parse::<Snafu>(), Err(ParseDigitError('3')));
     }
-    digits.push(base10 as u8);
-    digits
-}
 
-fn to_snafu(base10: u64) -> String {
-    let mut base5: Vec<u8> = to_base5(base10);
-    let mut snafu = vec![];
-    while let Some((char, rest)) = process_digit(&base5) {
-        snafu.push(char);
-        base5 = rest;
+    #[test]
+    fn test_arithmetic() {
+        let values = [-314159265i64, -2022, -3, -1, 0, 1, 4, 12345, 99999];
+        for a in values {
+            for b in values {
+                let (x, y) = (Snafu::from(a), Snafu::from(b));
+                assert_eq!(&x + &y, Snafu::from(a + b));
+                assert_eq!(&x - &y, Snafu::from(a - b));
+                assert_eq!(&x * &y, Snafu::from(a * b));
+                assert_eq!(x.cmp(&y), a.cmp(&b));
+            }
+            assert_eq!(-Snafu::from(a), Snafu::from(-a));
+        }
+        assert_eq!(Snafu::from(-3).to_string(), "-2");
     }
-    snafu.into_iter().rev().collect::<String>()
-}
 
-fn process_digit(tail: &[u8]) -> Option<(char, Vec<u8>)> {
-    if tail.len() == 0 {
-        None
-    } else {
-        Some(match *tail.last().unwrap() {
-            0 => ('0', tail[..tail.len()-1].to_vec()),
-            1 => ('1', tail[..tail.len()-1].to_vec()),
-            2 => ('2', tail[..tail.len()-1].to_vec()),
-            3 => (
-                '=',
-                to_base5(base5_to_10(&tail[..tail.len()-1]) + 1)
-            ),
-            4 => (
-                '-',
-                to_base5(base5_to_10(&tail[..tail.len()-1]) + 1)
-            ),
-            _ => unreachable!(),
-        })
+    #[test]
+    fn test_balanced_ternary() {
+        assert_eq!(BalancedTernary::from(8).to_string(), "10-");
+        assert_eq!(BalancedTernary::from(-8).to_string(), "-01");
+        assert_eq!("1-0".parse::<BalancedTernary>(), Ok(6.into()));
+        assert_eq!("2".parse::<BalancedTernary>(), Err(ParseDigitError('2')));
+        let big: Snafu = "2=-01".repeat(20).parse().unwrap();
+        assert_eq!(big.convert::<3>().convert::<5>(), big);
     }
-}
 
-fn part_one(filename: &str)  {
-    let file = File::open(filename).unwrap();
-    let mut reader = BufReader::new(file);
-    let mut line = String::new();
-    let mut base10_sum = 0u64;
-    while let Ok(length) = reader.read_line(&mut line) {
-        if length == 0 {
-            break;
+    #[test]
+    fn test_limits() {
+        for value in [i64::MIN, i64::MIN + 1, i64::MAX] {
+            let snafu = Snafu::from(value);
+            assert_eq!(i64::try_from(&snafu), Ok(value));
+            assert_eq!(i64::try_from(&BalancedTernary::from(value)), Ok(value));
         }
-        base10_sum += snafu_to_base10(line.trim());
-        line.clear();
+        let max = Snafu::from(i64::MAX);
+        assert_eq!(i64::try_from(&(&max + &Snafu::from(1))), Err(()));
+        assert_eq!(&(&max + &max) - &max, max);
     }
-    println!("Part one: {}", to_snafu(base10_sum));
-}
-fn main() {
-    part_one("input.txt");
 }
@@ -1,79 +1,37 @@
-use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-#[derive(Debug, Clone)]
-enum Visibility {
-    Hidden(u8),
-    Visible,
-    Unknown,
-}
-
-impl Default for Visibility {
-    fn default() -> Self {
-        Visibility::Unknown
-    }
-}
-
-enum VisibilityType {
-    Above,
-    Below,
-    Left,
-    Right,
-}
+/// Shades of the heatmap from the lowest scenic score to the highest
+const SHADES: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
 
-#[derive(Debug, Clone, Default)]
-struct TotalVisibility {
-    above: Visibility,
-    from_left: Visibility,
-    below: Visibility,
-    from_right: Visibility,
-}
-
-impl TotalVisibility {
-    fn all_hidden(&self) -> bool {
-        if let TotalVisibility {
-            above: Visibility::Hidden(_),
-            from_left: Visibility::Hidden(_),
-            below: Visibility::Hidden(_),
-            from_right: Visibility::Hidden(_),
-        } = self {
-            true
-        } else {
-            false
-        }
-    }
-
-    fn get(&self, ty: VisibilityType) -> &Visibility {
-        match ty {
-            VisibilityType::Above => &self.above,
-            VisibilityType::Below => &self.below,
-            VisibilityType::Left => &self.from_left,
-            VisibilityType::Right => &self.from_right,
-        }
-    }
+#[derive(Debug, Clone)]
+struct Forest {
+    rows: usize,
+    cols: usize,
+    heights: Vec<u8>,
 }
 
-fn parse_forest(file_name: &str) -> Result<Vec<Vec<u8>>, std::io::Error> {
+fn parse_forest(file_name: &str) -> Result<Forest, std::io::Error> {
     // open target file
     let file = File::open(file_name)?;
 
-    let mut forest = vec![];
+    let mut heights = vec![];
+    let mut rows = 0;
     // uses a reader buffer
     let mut reader = BufReader::new(file);
     let mut line = String::new();
     loop {
         match reader.read_line(&mut line) {
             Ok(bytes_read) => {
-                // EOF: save last file address to restart from this address for next run
                 if bytes_read == 0 {
                     break;
                 }
-                forest.push(line
-                    .chars()
-                    .filter_map(|c| u8::from_str_radix(&c.to_string(), 10).ok())
-                    .collect());
-
+                let before = heights.len();
+                heights.extend(line.chars().filter_map(|c| c.to_digit(10)).map(|h| h as u8));
+                if heights.len() > before {
+                    rows += 1;
+                }
                 line.clear();
             }
             Err(err) => {
@@ -81,184 +39,161 @@ fn parse_forest(file_name: &str) -> Result<Vec<Vec<u8>>, std::io::Error> {
             }
         };
     }
-    Ok(forest)
-}
-
-fn determine_visibility(
-    height: u8,
-    row: usize,
-    col: usize,
-    forest: &[Vec<u8>],
-    visible: &HashMap<(usize, usize), TotalVisibility>,
-    ty: VisibilityType,
-) -> Visibility {
-    if let Visibility::Hidden(hght) = visible[&(row, col)].get(ty) {
-        if hght >= &height {
-            Visibility::Hidden(*hght)
-        } else {
-            Visibility::Visible
-        }
-    } else if forest[row][col] >= height {
-        Visibility::Hidden(forest[row][col])
-    } else {
-        Visibility::Visible
+    if rows == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "The forest has no trees",
+        ));
     }
+    Ok(Forest {
+        rows,
+        cols: heights.len() / rows,
+        heights,
+    })
 }
 
-fn run(file_name: &str) -> HashMap<(usize, usize), TotalVisibility>
-{
-    let forest = parse_forest(file_name).unwrap();
-    let rows = forest.len();
-    let cols = forest[0].len();
-
-    let mut visible = HashMap::new();
-    for (row, line) in forest.iter().enumerate() {
-        for (col, height) in line.iter().enumerate() {
-            let above = if row == 0 {
-                Visibility::Visible
-            } else {
-                determine_visibility(
-                    *height,
-                    row - 1,
-                    col,
-                    &forest,
-                    &visible,
-                    VisibilityType::Above,
-                )
-            };
-
-            let from_left = if col == 0 {
-                Visibility::Visible
-            } else {
-                determine_visibility(
-                    *height,
-                    row,
-                    col - 1,
-                    &forest,
-                    &visible,
-                    VisibilityType::Left,
-                )
-            };
+/// Which trees can be seen from outside the forest and how far each
+/// tree can see in every direction.
+#[derive(Debug, Clone)]
+struct Survey {
+    rows: usize,
+    cols: usize,
+    visible: Vec<bool>,
+    /// Viewing distances looking up, down, left and right
+    views: Vec<[u64; 4]>,
+}
 
-            visible.insert((row, col), TotalVisibility {
-                above,
-                from_left,
-                ..Default::default()
-            });
+impl Survey {
+    fn new(forest: &Forest) -> Self {
+        let (rows, cols) = (forest.rows, forest.cols);
+        let mut survey = Self {
+            rows,
+            cols,
+            visible: vec![false; rows * cols],
+            views: vec![[0; 4]; rows * cols],
+        };
+        for col in 0..cols {
+            let line: Vec<usize> = (0..rows).map(|row| row * cols + col).collect();
+            survey.sweep(forest, &line, 0);
+            survey.sweep(forest, &line.into_iter().rev().collect::<Vec<_>>(), 1);
+        }
+        for row in 0..rows {
+            let line: Vec<usize> = (row * cols..(row + 1) * cols).collect();
+            survey.sweep(forest, &line, 2);
+            survey.sweep(forest, &line.into_iter().rev().collect::<Vec<_>>(), 3);
+        }
+        survey
+    }
 
+    /// Walk along a line of trees keeping a stack of the trees that are
+    /// not yet blocked by a taller one. Whatever is left on the stack
+    /// below a tree is the first tree at least as tall looking back
+    /// along the line.
+    fn sweep(&mut self, forest: &Forest, line: &[usize], direction: usize) {
+        let mut stack: Vec<usize> = Vec::with_capacity(line.len());
+        for (pos, ix) in line.iter().enumerate() {
+            let height = forest.heights[*ix];
+            while let Some(top) = stack.last() {
+                if forest.heights[line[*top]] < height {
+                    stack.pop();
+                } else {
+                    break;
+                }
+            }
+            match stack.last() {
+                Some(top) => self.views[*ix][direction] = (pos - top) as u64,
+                None => {
+                    self.views[*ix][direction] = pos as u64;
+                    self.visible[*ix] = true;
+                }
+            }
+            stack.push(pos);
         }
     }
 
-    for (row, line) in forest.iter().rev().enumerate() {
-        for (col, height) in line.iter().rev().enumerate() {
-            let row = rows - row - 1;
-            let col = cols - col - 1;
-            let below = if row == rows - 1 {
-                Visibility::Visible
-            } else {
-                determine_visibility(
-                    *height,
-                    row + 1,
-                    col,
-                    &forest,
-                    &visible,
-                    VisibilityType::Below,
-                )
-            };
+    fn visible_count(&self) -> usize {
+        self.visible.iter().filter(|v| **v).count()
+    }
 
-            let from_right = if col == cols - 1 {
-                Visibility::Visible
-            } else {
-                determine_visibility(
-                    *height,
-                    row,
-                    col + 1,
-                    &forest,
-                    &visible,
-                    VisibilityType::Right,
-                )
-            };
-            visible.entry((row, col)).and_modify(|vis| {
-                vis.below = below;
-                vis.from_right = from_right;
-            });
-        }
+    fn scenic_score(&self, (row, col): (usize, usize)) -> u64 {
+        self.views[row * self.cols + col].iter().product()
     }
-    visible
-}
 
-fn part_one(filename: &str) {
-    let visible = run(filename);
-    let mut count = visible.len();
-    for vis in visible.values() {
-        if vis.all_hidden() {
-            count -= 1;
-        }
+    /// The position and score of the tree with the best view
+    fn best_tree(&self) -> ((usize, usize), u64) {
+        (0..self.rows * self.cols)
+            .map(|ix| (ix / self.cols, ix % self.cols))
+            .map(|pos| (pos, self.scenic_score(pos)))
+            .max_by_key(|(_, score)| *score)
+            .unwrap()
     }
-    println!("Hidden trees: {}", count);
 }
 
-fn part_two(filename: &str) {
-    let forest = parse_forest(filename).unwrap();
-    let mut max_score = 0u64;
-    for row in 0..forest.len() {
-        for col in 0..forest[0].len() {
-            max_score = std::cmp::max(
-                max_score,
-                scenic_score(&forest, (row, col))
-            );
+/// A heatmap of the scenic scores on a logarithmic scale with the best
+/// tree marked by an `X`.
+impl Display for Survey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (best, max) = self.best_tree();
+        let top = ((max + 1) as f64).ln();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let score = self.scenic_score((row, col));
+                let shade = if (row, col) == best {
+                    'X'
+                } else if score == 0 {
+                    SHADES[0]
+                } else {
+                    let level = ((score + 1) as f64).ln() / top * (SHADES.len() - 1) as f64;
+                    SHADES[(level.ceil() as usize).clamp(1, SHADES.len() - 1)]
+                };
+                write!(f, "{}", shade)?;
+            }
+            writeln!(f)?;
         }
+        Ok(())
     }
-    println!("Max scenic score: {}", max_score);
 }
 
-fn scenic_score(forest: &[Vec<u8>], pos: (usize, usize)) -> u64 {
-    let height = forest[pos.0][pos.1];
-    let (mut row, col) = (pos.0 as i64 - 1, pos.1);
-    let mut above_dist =  0;
-    while row >= 0 {
-        above_dist += 1;
-        if forest[row as usize][col] < height {
-            row -= 1;
-        } else {
-            break;
-        }
-    }
-    let (mut row, col) = (pos.0 + 1, pos.1);
-    let mut below_dist = 0;
-    while row < forest.len() {
-        below_dist += 1;
-        if forest[row][col] < height {
-            row += 1;
-        } else {
-            break;
-        }
-    }
+fn part_one(filename: &str) {
+    let survey = Survey::new(&parse_forest(filename).unwrap());
+    println!("Visible trees: {}", survey.visible_count());
+}
 
-    let (row, mut col) = (pos.0, pos.1 as i64 - 1);
-    let mut left_dist = 0;
-    while col >= 0 {
-        left_dist += 1;
-        if forest[row][col as usize] < height {
-            col -= 1;
-        } else {
-            break;
-        }
-    }
-    let (row, mut col) = (pos.0, pos.1 + 1);
-    let mut right_dist = 0;
-    while col < forest[0].len() {
-        right_dist += 1;
-        if forest[row][col] < height {
-            col += 1;
-        } else {
-            break;
-        }
-    }
-    above_dist * below_dist * left_dist * right_dist
+fn part_two(filename: &str) {
+    let survey = Survey::new(&parse_forest(filename).unwrap());
+    let ((row, col), score) = survey.best_tree();
+    println!("Max scenic score: {} at row {}, column {}", score, row, col);
 }
 
 fn main() {
     part_one("input.txt");
     part_two("input.txt");
+    if std::env::args().any(|arg| arg == "--heatmap") {
+        print!("{}", Survey::new(&parse_forest("input.txt").unwrap()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_survey() {
+        let survey = Survey::new(&parse_forest("test.txt").unwrap());
+        assert_eq!(survey.visible_count(), 21);
+        assert_eq!(survey.views[survey.cols + 2], [1, 2, 1, 2]);
+        assert_eq!(survey.scenic_score((1, 2)), 4);
+        assert_eq!(survey.scenic_score((3, 2)), 8);
+        assert_eq!(survey.best_tree(), ((3, 2), 8));
+        assert_eq!(
+            survey.to_string().lines().nth(3).unwrap().chars().nth(2),
+            Some('X')
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let err = parse_forest("empty.txt").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
 }
@@ -1,7 +1,9 @@
-use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+type Pos = (usize, usize);
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum PipeType {
     Horizontal,
@@ -14,55 +16,51 @@ enum PipeType {
     Start,
 }
 
+/// The pipes the start tile could be hiding
+const PIPES: [PipeType; 6] = [
+    PipeType::Horizontal,
+    PipeType::Vertical,
+    PipeType::J,
+    PipeType::L,
+    PipeType::F,
+    PipeType::Seven,
+];
+
 impl PipeType {
     /// Given a pipe and a position in the infinite positive quadrant,
     /// find the (up to) two neighboring positions
-    fn endpoints(&self, pos: (usize, usize)) -> [Option<(usize, usize)>; 2] {
+    fn endpoints(&self, pos: Pos) -> [Option<Pos>; 2] {
+        let north = pos.0.checked_sub(1).map(|row| (row, pos.1));
+        let south = Some((pos.0 + 1, pos.1));
+        let west = pos.1.checked_sub(1).map(|col| (pos.0, col));
+        let east = Some((pos.0, pos.1 + 1));
         match self {
-            PipeType::Horizontal => {
-                if pos.1 == 0 {
-                    [None, Some((pos.0, pos.1 + 1))]
-                } else {
-                    [Some((pos.0, pos.1 - 1)), Some((pos.0, pos.1 + 1))]
-                }
-            }
-            PipeType::Start | PipeType::Vertical => {
-                if pos.0 == 0 {
-                    [None, Some((pos.0 + 1, pos.1))]
-                } else {
-                    [Some((pos.0 + 1, pos.1)), Some((pos.0 - 1, pos.1))]
-                }
-            }
-            PipeType::J => [
-                if pos.0 == 0 {
-                    None
-                } else {
-                    Some((pos.0 - 1, pos.1))
-                },
-                if pos.1 == 0 {
-                    None
-                } else {
-                    Some((pos.0, pos.1 - 1))
-                },
-            ],
-            PipeType::L => [
-                if pos.0 == 0 {
-                    None
-                } else {
-                    Some((pos.0 - 1, pos.1))
-                },
-                Some((pos.0, pos.1 + 1)),
-            ],
-            PipeType::F => [Some((pos.0, pos.1 + 1)), Some((pos.0 + 1, pos.1))],
-            PipeType::Seven => [
-                if pos.1 == 0 {
-                    None
-                } else {
-                    Some((pos.0, pos.1 - 1))
-                },
-                Some((pos.0 + 1, pos.1)),
-            ],
-            PipeType::None => [None, None],
+            PipeType::Horizontal => [west, east],
+            PipeType::Vertical => [south, north],
+            PipeType::J => [north, west],
+            PipeType::L => [north, east],
+            PipeType::F => [east, south],
+            PipeType::Seven => [west, south],
+            PipeType::None | PipeType::Start => [None, None],
+        }
+    }
+
+    /// Pipes that cross a line running along the row just above the
+    /// middle of the tiles
+    fn goes_north(&self) -> bool {
+        matches!(self, PipeType::Vertical | PipeType::J | PipeType::L)
+    }
+
+    fn box_drawing(&self) -> char {
+        match self {
+            PipeType::Horizontal => '─',
+            PipeType::Vertical => '│',
+            PipeType::J => '┘',
+            PipeType::L => '└',
+            PipeType::F => '┌',
+            PipeType::Seven => '┐',
+            PipeType::None => ' ',
+            PipeType::Start => 'S',
         }
     }
 }
@@ -86,39 +84,19 @@ impl From<char> for PipeType {
 #[derive(Debug)]
 struct Pipe {
     pipe_type: PipeType,
-    pos: (usize, usize),
+    pos: Pos,
 }
 
 impl Pipe {
-
     /// Get the (up to two) neighbors of this pipe
-    fn endpoints(&self) -> [Option<(usize, usize)>; 2] {
+    fn endpoints(&self) -> [Option<Pos>; 2] {
         self.pipe_type.endpoints(self.pos)
     }
 
     /// Check if this pipe and another pipe contain each other
     /// as neighbors
     fn adjacent(&self, other: &Self) -> bool {
-        let mut a_sees_b = false;
-        let mut b_sees_a = false;
-        for neighbor in  self.endpoints() {
-            if let Some(neighbor) = neighbor {
-                if neighbor == other.pos {
-                    a_sees_b = true;
-                    break;
-                }
-            }
-        }
-
-        for neighbor in other.endpoints() {
-            if let Some(neighbor) = neighbor {
-                if neighbor == self.pos {
-                    b_sees_a = true;
-                    break;
-                }
-            }
-        }
-        a_sees_b && b_sees_a
+        self.endpoints().contains(&Some(other.pos)) && other.endpoints().contains(&Some(self.pos))
     }
 }
 
@@ -127,136 +105,152 @@ struct Grid {
     grid: Vec<Vec<Pipe>>,
     rows: usize,
     cols: usize,
+    start: Pos,
 }
 
 impl Grid {
     /// Adds the upper bounds check of this grid to the neighbors of the
     /// pipe given by `pos`
-    fn endpoints(&self, pos: (usize, usize)) -> [Option<(usize, usize)>; 2] {
-        let pipe = &self.grid[pos.0][pos.1];
-        let mut ends = pipe.endpoints();
-        ends[0] = if let Some(e) = ends[0] {
-            if e.0 < self.rows && e.1 < self.cols {
-                Some(e)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-        ends[1] = if let Some(e) = ends[1] {
-            if e.0 < self.rows && e.1 < self.cols {
-                Some(e)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-        ends
+    fn endpoints(&self, pos: Pos) -> [Option<Pos>; 2] {
+        self.grid[pos.0][pos.1]
+            .endpoints()
+            .map(|end| end.filter(|e| e.0 < self.rows && e.1 < self.cols))
     }
 
     /// Gets the (up to two) pipes connecting to the pipe at `pos`
-    fn neighbors(&self, pos: (usize, usize)) -> [Option<(usize, usize)>; 2] {
+    fn neighbors(&self, pos: Pos) -> [Option<Pos>; 2] {
         let pipe = &self.grid[pos.0][pos.1];
-        let mut ends = self.endpoints(pos);
-        ends[0] = if let Some(e) = ends[0] {
-          if pipe.adjacent(&self.grid[e.0][e.1]) {
-              Some(e)
-          } else {
-              None
-          }
-        } else {
-            None
-        };
-        ends[1] = if let Some(e) = ends[1] {
-          if pipe.adjacent(&self.grid[e.0][e.1]) {
-              Some(e)
-          } else {
-              None
-          }
-        } else {
-            None
-        };
-        ends
+        self.endpoints(pos)
+            .map(|end| end.filter(|e| pipe.adjacent(&self.grid[e.0][e.1])))
     }
-}
-
-#[derive(Debug)]
-struct Visitor {
-    start: (usize, usize),
-    previous: (usize, usize),
-    current: (usize, usize),
-    path: HashSet<(usize, usize)>,
-}
 
-impl Visitor {
-
-    fn new(start: (usize, usize)) -> Self {
-        Self {
-            start,
-            previous: start,
-            current: start,
-            path: HashSet::from([start]),
+    /// Follow the pipes from the start tile until they lead back to it
+    fn trace(&self) -> Option<Loop> {
+        let mut tiles = vec![self.start];
+        let mut previous = None;
+        let mut current = self.start;
+        loop {
+            let next = self
+                .neighbors(current)
+                .into_iter()
+                .flatten()
+                .find(|next| Some(*next) != previous)?;
+            if next == self.start {
+                return Some(Loop { tiles });
+            }
+            previous = Some(current);
+            current = next;
+            tiles.push(current);
         }
     }
 
-    fn step(&mut self, grid: &Grid) {
-        let next = grid.neighbors(self.current);
-        if let Some(next) = next[0] {
-            if next != self.previous {
-                self.previous = self.current;
-                self.current = next;
-                self.path.insert(self.current);
-                return
+    /// Replace the start tile by the pipe that closes the loop through it
+    fn infer_start(&mut self) -> Loop {
+        let (row, col) = self.start;
+        for pipe_type in PIPES {
+            self.grid[row][col].pipe_type = pipe_type;
+            if let Some(pipe_loop) = self.trace() {
+                return pipe_loop;
             }
         }
-        if let Some(next) = next[1] {
-            if next != self.previous {
-                self.previous = self.current;
-                self.current = next;
-                self.path.insert(self.current);
-                return
+        panic!("No pipe at the start tile closes a loop")
+    }
+
+    /// The tiles that lie inside the loop, found by counting crossings
+    /// of the loop along each row
+    fn inside(&self, pipe_loop: &Loop) -> Vec<Vec<bool>> {
+        let mut on_loop = vec![vec![false; self.cols]; self.rows];
+        for (row, col) in &pipe_loop.tiles {
+            on_loop[*row][*col] = true;
+        }
+        let mut inside = vec![vec![false; self.cols]; self.rows];
+        for row in 0..self.rows {
+            let mut crossings = 0;
+            for col in 0..self.cols {
+                if on_loop[row][col] {
+                    if self.grid[row][col].pipe_type.goes_north() {
+                        crossings += 1;
+                    }
+                } else {
+                    inside[row][col] = crossings % 2 == 1;
+                }
             }
         }
-        panic!("Could not advance to the next step in the loop. This is a bug!")
-
+        inside
     }
 
-    fn find_loop(&mut self, grid: &Grid) -> usize {
-        self.step(grid);
-        while self.current != self.start {
-            self.step(grid);
+    fn render<'a>(&'a self, pipe_loop: &'a Loop) -> Render<'a> {
+        Render {
+            grid: self,
+            pipe_loop,
         }
-        self.path.len() / 2
     }
+}
+
+/// The tiles of the loop in the order they are visited, starting at the
+/// start tile. These are the vertices of a polygon.
+#[derive(Debug, Clone)]
+struct Loop {
+    tiles: Vec<Pos>,
+}
+
+impl Loop {
+    fn farthest(&self) -> usize {
+        self.tiles.len() / 2
+    }
+
+    /// Twice the area of the polygon by the shoelace formula
+    fn double_area(&self) -> i64 {
+        let n = self.tiles.len();
+        (0..n)
+            .map(|ix| {
+                let (r1, c1) = self.tiles[ix];
+                let (r2, c2) = self.tiles[(ix + 1) % n];
+                r1 as i64 * c2 as i64 - r2 as i64 * c1 as i64
+            })
+            .sum::<i64>()
+            .abs()
+    }
+
+    /// Pick's theorem says `A = I + B / 2 - 1` for the area `A`, the
+    /// interior points `I` and the boundary points `B` of a lattice polygon.
+    fn enclosed(&self) -> usize {
+        ((self.double_area() - self.tiles.len() as i64) / 2 + 1) as usize
+    }
+}
+
+/// The maze drawn with box-drawing characters, with the loop in bold
+/// yellow, the enclosed tiles in green and the outside tiles dimmed
+struct Render<'a> {
+    grid: &'a Grid,
+    pipe_loop: &'a Loop,
+}
 
-    fn in_loop(&self, mut pos: (usize, usize), grid: &Grid) -> bool {
-        let mut in_loop = false;
-        if pos.0 == 0 || pos.1 == 0 {
-            return false;
+impl Display for Render<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let inside = self.grid.inside(self.pipe_loop);
+        let mut on_loop = vec![vec![false; self.grid.cols]; self.grid.rows];
+        for (row, col) in &self.pipe_loop.tiles {
+            on_loop[*row][*col] = true;
         }
-        pos = (pos.0 - 1, pos.1 - 1);
-        loop {
-            if self.path.contains(&pos) {
-                match &grid.grid[pos.0][pos.1].pipe_type {
-                    PipeType::Horizontal | PipeType::Vertical | PipeType::F | PipeType::J | PipeType::Start => {
-                        in_loop = !in_loop;
-                    }
-                    _ => {}
+        for (row, line) in self.grid.grid.iter().enumerate() {
+            for (col, pipe) in line.iter().enumerate() {
+                let c = pipe.pipe_type.box_drawing();
+                if on_loop[row][col] {
+                    write!(f, "\x1b[1;33m{}\x1b[0m", c)?;
+                } else if inside[row][col] {
+                    write!(f, "\x1b[42m{}\x1b[0m", c)?;
+                } else {
+                    write!(f, "\x1b[2m{}\x1b[0m", c)?;
                 }
             }
-            if pos.0 == 0 || pos.1 == 0 {
-                break;
-            }
-            pos = (pos.0 - 1, pos.1 - 1);
+            writeln!(f)?;
         }
-        in_loop
+        Ok(())
     }
-
 }
 
-fn parse_file(filename: &str) -> (Grid, Visitor) {
+fn parse_file(filename: &str) -> Grid {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
@@ -267,51 +261,71 @@ fn parse_file(filename: &str) -> (Grid, Visitor) {
         if length == 0 {
             break;
         }
-        let grid_line: Vec<_> = line.trim().chars().enumerate().map(|(col, c)|{
-            if c == 'S' {
-                start = (row, col);
-            }
-            Pipe{
-                pipe_type: PipeType::from(c),
-                pos: (row, col),
-            }
-        } ).collect();
+        let grid_line: Vec<_> = line
+            .trim()
+            .chars()
+            .enumerate()
+            .map(|(col, c)| {
+                if c == 'S' {
+                    start = (row, col);
+                }
+                Pipe {
+                    pipe_type: PipeType::from(c),
+                    pos: (row, col),
+                }
+            })
+            .collect();
         grid.push(grid_line);
         row += 1;
         line.clear()
     }
     let cols = grid[0].len();
-    (Grid {
+    Grid {
         grid,
         rows: row,
         cols,
-    }, Visitor::new(start))
+        start,
+    }
 }
 
 fn part_one(filename: &str) {
-    let (grid, mut visitor) = parse_file(filename);
-    let steps = visitor.find_loop(&grid);
-    println!("Part one: {}", steps);
+    let mut grid = parse_file(filename);
+    let pipe_loop = grid.infer_start();
+    println!("Part one: {}", pipe_loop.farthest());
 }
 
 fn part_two(filename: &str) {
-    let (grid, mut visitor) = parse_file(filename);
-    visitor.find_loop(&grid);
-    let mut total = 0u64;
-    for row in 0..grid.rows {
-        for col in 0..grid.cols {
-            if !visitor.path.contains(&(row, col)) {
-                if visitor.in_loop((row, col), &grid) {
-                    total += 1;
-                }
-            }
-        }
+    let mut grid = parse_file(filename);
+    let pipe_loop = grid.infer_start();
+    println!("Part two: {}", pipe_loop.enclosed());
+    if std::env::args().any(|arg| arg == "--render") {
+        print!("{}", grid.render(&pipe_loop));
     }
-
-    println!("Part two: {}", total);
 }
 
 fn main() {
     part_one("input.txt");
     part_two("input.txt")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loop() {
+        let mut grid = parse_file("test.txt");
+        let pipe_loop = grid.infer_start();
+        assert_eq!(
+            grid.grid[grid.start.0][grid.start.1].pipe_type,
+            PipeType::Vertical
+        );
+        assert_eq!(pipe_loop.enclosed(), 8);
+        let inside = grid.inside(&pipe_loop);
+        let count = inside.iter().flatten().filter(|tile| **tile).count();
+        assert_eq!(count, 8);
+        let render = grid.render(&pipe_loop).to_string();
+        assert_eq!(render.lines().count(), grid.rows);
+        assert!(render.contains('│') && !render.contains('S'));
+    }
+}
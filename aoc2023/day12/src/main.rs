@@ -1,14 +1,44 @@
-use std::collections::{HashSet, VecDeque};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
-use rayon::prelude::*;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum Completion {
-    Invalid,
-    Partial,
-    Finished,
+mod nonogram;
+
+use nonogram::Nonogram;
+
+/// Count the ways to fill in the `?`s of a line so that its runs of `#`
+/// are exactly the given chunks.
+///
+/// `ways[i][j]` is the number of ways to place `chunks[j..]` in
+/// `line[i..]`. A chunk can start at `i` if the next `len` cells hold
+/// no `.` and it is not directly followed by a `#`.
+fn count_arrangements(line: &[char], chunks: &[usize]) -> u64 {
+    let n = line.len();
+    // dots[i] is the number of `.` in line[..i]
+    let mut dots = vec![0; n + 1];
+    for (ix, c) in line.iter().enumerate() {
+        dots[ix + 1] = dots[ix] + (*c == '.') as usize;
+    }
+    let mut ways = vec![vec![0u64; chunks.len() + 1]; n + 2];
+    ways[n][chunks.len()] = 1;
+    ways[n + 1][chunks.len()] = 1;
+    for i in (0..n).rev() {
+        for j in 0..=chunks.len() {
+            let mut total = 0;
+            if line[i] != '#' {
+                total = ways[i + 1][j];
+            }
+            if let Some(len) = chunks.get(j) {
+                let end = i + len;
+                if line[i] != '.' && end <= n && dots[end] == dots[i] && line.get(end) != Some(&'#')
+                {
+                    total = u64::saturating_add(total, ways[end + 1][j + 1]);
+                }
+            }
+            ways[i][j] = total;
+        }
+    }
+    ways[0][0]
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -18,154 +48,28 @@ struct Record {
 }
 
 impl Record {
-
-    fn unfold(self) -> Self {
-        let Self {
-            mut springs,
-            mut chunks,
-        } = self;
-        let original = springs.clone();
-        let original_chunks = chunks.clone();
-        for _ in 0..4 {
-            springs.push('?');
-            springs.extend(original.clone().into_iter());
-            chunks.extend(original_chunks.clone().into_iter());
-        }
-        Self {
-            springs,
-            chunks
-        }
-    }
-
-    fn neighbors(&self, cache: &mut HashSet<Record>) -> (VecDeque<Self>, u64) {
-        let mut ngbrs = VecDeque::new();
-        let mut finished = 0;
-        for (ix, c) in self.springs.iter().enumerate() {
-            if *c == '?' {
-                let mut springs = self.springs.clone();
-                springs[ix] = '.';
-                let mut record = Record {
-                    springs: springs.clone(),
-                    chunks: self.chunks.clone(),
-                };
-                if !cache.contains(&record) {
-                    match record.check_chunks(){
-                        Completion::Partial => ngbrs.push_back(record),
-                        Completion::Finished => {
-                            record.complete();
-                            if cache.insert(record) {
-                                finished += 1;
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-
-                springs[ix] = '#';
-                let mut record = Record {
-                    springs,
-                    chunks: self.chunks.clone(),
-                };
-                if !cache.contains(&record) {
-                    match record.check_chunks() {
-                        Completion::Partial => ngbrs.push_back(record),
-                        Completion::Finished => {
-                            record.complete();
-                            if cache.insert(record) {
-                                finished += 1;
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                break;
-            }
-        }
-        (ngbrs, finished)
-    }
-
-    fn complete(&mut self) {
-        for c in self.springs.iter_mut() {
-            if *c == '?' {
-                *c = '.';
-            }
-        }
-    }
-
-    fn check_chunks(&self) -> Completion {
-        let mut chunks = vec![];
-        let mut next = 0;
-
-        if self.springs.iter().filter(|c| **c == '#').count()
-            > self.chunks.iter().sum::<u8>() as usize
-        {
-            return Completion::Invalid;
-        }
-        if self.springs.iter().filter(|c| **c == '#' || **c == '?').count()
-            < self.chunks.iter().sum::<u8>() as usize
-        {
-            return Completion::Invalid;
-        }
-        for c in self.springs.iter() {
-            match c {
-                '#' => {
-                    next += 1;
-                }
-                '.' => {
-                    if next != 0 {
-                        chunks.push(next);
-                        next = 0;
-                    }
-                }
-                _ => {
-                    if next != 0 {
-                        chunks.push(next);
-                        next = 0;
-                    }
-                    break
-                }
+    /// Repeat the springs `factor` times joined by `?` along with the chunks
+    fn unfold(self, factor: usize) -> Self {
+        let Self { springs, chunks } = self;
+        let mut unfolded = Vec::with_capacity(factor * (springs.len() + 1));
+        for ix in 0..factor {
+            if ix > 0 {
+                unfolded.push('?');
             }
+            unfolded.extend_from_slice(&springs);
         }
-        if next != 0 {
-            chunks.push(next);
-        }
-        if chunks.is_empty() {
-            return Completion::Partial;
-        }
-
-        if chunks == self.chunks {
-            Completion::Finished
-        } else if !chunks.len() <= self.chunks.len()
-            || !chunks[..chunks.len() - 1]
-                .iter()
-                .zip(self.chunks.iter())
-                .all(|(a, b)| a == b)
-            || chunks[chunks.len() - 1] > self.chunks[chunks.len() - 1]
-        {
-            Completion::Invalid
-        } else {
-            Completion::Partial
+        Self {
+            springs: unfolded,
+            chunks: chunks.repeat(factor),
         }
     }
 
-    fn count_combos(&self, cache: Option<HashSet<Record>>) -> (HashSet<Record>, u64) {
-        let mut cache = cache.unwrap_or_default();
-        let (mut stack, mut total) = self.neighbors(&mut cache);
-        while let Some(next) = stack.pop_back() {
-            if cache.contains(&next) {
-                continue;
-            }
-            let (next_ngbrs, next_total) = next.neighbors(&mut cache);
-            cache.insert(next);
-            stack.extend(next_ngbrs.into_iter());
-            total += next_total;
-        }
-        (cache, total)
+    fn count_combos(&self) -> u64 {
+        let chunks: Vec<usize> = self.chunks.iter().map(|c| *c as usize).collect();
+        count_arrangements(&self.springs, &chunks)
     }
 }
 
-
-
 fn parse_file(filename: &str) -> Vec<Record> {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
@@ -176,35 +80,44 @@ fn parse_file(filename: &str) -> Vec<Record> {
         if length == 0 {
             break;
         }
-        let mut split = line.trim().split(' ');
-        let springs = split.next().unwrap().to_string().chars().collect();
-        let chunks = split
-            .next()
-            .unwrap()
-            .split(',')
-            .map(|c| u8::from_str(c).unwrap())
-            .collect();
-        records.push( Record { springs, chunks } );
+        if let Some((springs, chunks)) = line.trim().split_once(' ') {
+            let springs = springs.chars().collect();
+            let chunks = chunks
+                .split(',')
+                .map(|c| u8::from_str(c).unwrap())
+                .collect();
+            records.push(Record { springs, chunks });
+        }
         line.clear()
     }
     records
 }
 
-fn part_one() {
-    let records = parse_file("input.txt");
-    let ans = records.into_iter().map(|r| r.count_combos(None).1).sum::<u64>();
-    println!("Part one: {}", ans);
+fn total_combos(filename: &str, factor: usize) -> u64 {
+    parse_file(filename)
+        .into_iter()
+        .map(|r| r.unfold(factor).count_combos())
+        .sum()
 }
 
-fn part_two() {
-    let records = parse_file("input.txt");
-    let ans = records.into_par_iter().map(|r| r.unfold().count_combos(None).1).sum::<u64>();
-    println!("Part two: {}", ans);
+fn part_one(filename: &str) {
+    println!("Part one: {}", total_combos(filename, 1));
+}
+
+fn part_two(filename: &str) {
+    println!("Part two: {}", total_combos(filename, 5));
 }
 
 fn main() {
-    part_one();
-    part_two();
+    part_one("input.txt");
+    part_two("input.txt");
+    if let Some(puzzle) = std::env::args().nth(1) {
+        let nonogram = Nonogram::from_str(&std::fs::read_to_string(puzzle).unwrap()).unwrap();
+        match nonogram.solve() {
+            Some(solution) => print!("{}", nonogram::render(&solution)),
+            None => println!("The nonogram has no solution"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -217,15 +130,14 @@ mod tests {
             springs: "???.###".chars().collect(),
             chunks: vec![1, 1, 3],
         };
-        let combos = record.count_combos(None).1;
-        assert_eq!(combos, 1);
+        assert_eq!(record.count_combos(), 1);
 
         let record = Record {
             springs: "?###????????".chars().collect(),
             chunks: vec![3, 2, 1],
         };
-        let combos = record.count_combos(None).1;
-        assert_eq!(combos, 10);
+        assert_eq!(record.count_combos(), 10);
+        assert_eq!(total_combos("test.txt", 1), 21);
     }
 
     #[test]
@@ -234,20 +146,40 @@ mod tests {
             springs: "?.#".chars().collect(),
             chunks: vec![1, 1],
         };
-        let record = record.unfold();
+        let record = record.unfold(5);
         let expected = Record {
             springs: "?.#??.#??.#??.#??.#".chars().collect(),
-             chunks: vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+            chunks: vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
         };
         assert_eq!(record, expected);
-        let combos = record.count_combos(None).1;
-        assert_eq!(combos, 1);
+        assert_eq!(record.count_combos(), 1);
 
         let record = Record {
             springs: "?###????????".chars().collect(),
             chunks: vec![3, 2, 1],
         };
-        let combos = record.unfold().count_combos();
-        assert_eq!(combos, 506250);
+        assert_eq!(record.unfold(5).count_combos(), 506250);
+        assert_eq!(total_combos("test.txt", 5), 525152);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_nonogram() {
+        let nonogram = Nonogram::from_str(&std::fs::read_to_string("test.non").unwrap()).unwrap();
+        let solution = nonogram.solve().unwrap();
+        assert_eq!(
+            nonogram::render(&solution),
+            ".#.#.\n#####\n#####\n.###.\n..#..\n"
+        );
+        assert!(
+            Nonogram::from_str("width 2\nheight 1\nrows\n3\ncolumns\n1\n1\n")
+                .unwrap()
+                .solve()
+                .is_none()
+        );
+        // a puzzle that line solving alone cannot finish
+        let ambiguous =
+            Nonogram::from_str("width 2\nheight 2\nrows\n1\n1\ncolumns\n1\n1\n").unwrap();
+        let solution = ambiguous.solve().unwrap();
+        assert_eq!(nonogram::render(&solution), "#.\n.#\n");
+    }
+}
@@ -0,0 +1,142 @@
+use super::*;
+
+/// A picture puzzle where the clues give the runs of filled cells in
+/// every row and column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nonogram {
+    pub rows: Vec<Vec<usize>>,
+    pub cols: Vec<Vec<usize>>,
+}
+
+type Board = Vec<Vec<char>>;
+
+/// Fill in every cell of a line that is the same in all arrangements
+/// matching the clue. Returns `None` if there are no such arrangements.
+fn solve_line(line: &[char], clue: &[usize]) -> Option<Vec<char>> {
+    if count_arrangements(line, clue) == 0 {
+        return None;
+    }
+    let mut solved = line.to_vec();
+    for ix in 0..line.len() {
+        if solved[ix] != '?' {
+            continue;
+        }
+        solved[ix] = '#';
+        let filled = count_arrangements(&solved, clue);
+        solved[ix] = '.';
+        let empty = count_arrangements(&solved, clue);
+        solved[ix] = match (filled, empty) {
+            (0, _) => '.',
+            (_, 0) => '#',
+            _ => '?',
+        };
+    }
+    Some(solved)
+}
+
+impl Nonogram {
+    /// Solve rows and columns on their own until nothing changes
+    fn propagate(&self, board: &mut Board) -> Option<()> {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (row, clue) in self.rows.iter().enumerate() {
+                let solved = solve_line(&board[row], clue)?;
+                if solved != board[row] {
+                    board[row] = solved;
+                    changed = true;
+                }
+            }
+            for (col, clue) in self.cols.iter().enumerate() {
+                let line: Vec<char> = board.iter().map(|row| row[col]).collect();
+                let solved = solve_line(&line, clue)?;
+                if solved != line {
+                    for (row, c) in solved.into_iter().enumerate() {
+                        board[row][col] = c;
+                    }
+                    changed = true;
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn search(&self, mut board: Board) -> Option<Board> {
+        self.propagate(&mut board)?;
+        let Some((row, col)) = (0..self.rows.len())
+            .flat_map(|row| (0..self.cols.len()).map(move |col| (row, col)))
+            .find(|(row, col)| board[*row][*col] == '?')
+        else {
+            return Some(board);
+        };
+        ['#', '.'].into_iter().find_map(|guess| {
+            let mut next = board.clone();
+            next[row][col] = guess;
+            self.search(next)
+        })
+    }
+
+    /// Line solving with backtracking whenever it gets stuck
+    pub fn solve(&self) -> Option<Board> {
+        self.search(vec![vec!['?'; self.cols.len()]; self.rows.len()])
+    }
+}
+
+pub fn render(board: &Board) -> String {
+    board
+        .iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+/// Parses the `.non` format: `width` and `height` lines followed by
+/// `rows` and `columns` sections with one comma separated clue per line.
+/// Empty lines are written as `0`. Other keys such as `title` are ignored.
+impl FromStr for Nonogram {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut width = None;
+        let mut height = None;
+        let mut rows = vec![];
+        let mut cols = vec![];
+        let mut lines = s.lines().map(str::trim);
+        while let Some(line) = lines.next() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let (clues, count) = match key {
+                "width" => {
+                    width = Some(value.parse::<usize>().map_err(|e| e.to_string())?);
+                    continue;
+                }
+                "height" => {
+                    height = Some(value.parse::<usize>().map_err(|e| e.to_string())?);
+                    continue;
+                }
+                "rows" => (
+                    &mut rows,
+                    height.ok_or("The height must come before the rows")?,
+                ),
+                "columns" => (
+                    &mut cols,
+                    width.ok_or("The width must come before the columns")?,
+                ),
+                _ => continue,
+            };
+            for _ in 0..count {
+                let line = lines.next().ok_or(format!("Missing clues after {}", key))?;
+                let clue = line
+                    .split(',')
+                    .map(|n| n.trim().parse::<usize>().map_err(|e| e.to_string()))
+                    .filter(|n| *n != Ok(0))
+                    .collect::<Result<Vec<_>, _>>()?;
+                clues.push(clue);
+            }
+        }
+        match (width, height) {
+            (Some(width), Some(height)) if cols.len() == width && rows.len() == height => {
+                Ok(Self { rows, cols })
+            }
+            _ => Err("Expected a width, a height and a clue for every row and column".into()),
+        }
+    }
+}
@@ -14,87 +14,91 @@ struct Pattern {
     cols: usize,
 }
 
+impl Line {
+    fn summary(&self) -> usize {
+        match self {
+            Line::Vertical(line) => line + 1,
+            Line::Horizontal(line) => 100 * line + 100,
+        }
+    }
+}
+
 impl Pattern {
-    fn is_symmetric(&self, line: Line) -> bool {
-        match line {
-            Line::Vertical(line) => {
-                let cols_to_check = std::cmp::min(line + 1, self.cols - 1 - line);
-                for row in 0..self.rows {
-                    let row = &self.elements[row];
-                    for col in 1..=cols_to_check {
-                        let upper = col + line;
-                        let lower = line + 1 - col;
-                        if row[upper] != row[lower] {
-                            return false;
-                        }
-                    }
-                }
-                true
-            }
-            Line::Horizontal(line) => {
-                let rows_to_check = std::cmp::min(line + 1, self.rows - 1 - line);
-                for row in 1..=rows_to_check {
-                    for col in 0..self.cols {
-                        let upper = row + line;
-                        let lower = line + 1 - row;
-                        if self.elements[upper][col] != self.elements[lower][col] {
-                            return false;
-                        }
-                    }
+    /// Each row and each column as a bitmask with `#` as a set bit
+    fn masks(&self) -> (Vec<u64>, Vec<u64>) {
+        assert!(self.rows <= 64 && self.cols <= 64, "Pattern is too big");
+        let mut rows = vec![0u64; self.rows];
+        let mut cols = vec![0u64; self.cols];
+        for (row, line) in self.elements.iter().enumerate() {
+            for (col, c) in line.iter().enumerate() {
+                if *c == '#' {
+                    rows[row] |= 1 << col;
+                    cols[col] |= 1 << row;
                 }
-                true
             }
         }
+        (rows, cols)
     }
 
-    fn find_symmetry(&self) -> Option<Line> {
-        for row in 0..self.rows - 1 {
-            if self.is_symmetric(Line::Horizontal(row)) {
-                return Some(Line::Horizontal(row));
-            }
-        }
-        for col in 0..self.cols - 1 {
-            if self.is_symmetric(Line::Vertical(col)) {
-                return Some(Line::Vertical(col));
+    /// The number of cells that differ from their reflection in the
+    /// line after `axis`, stopping early once it exceeds `limit`
+    fn differences(lines: &[u64], axis: usize, limit: u32) -> u32 {
+        let mut total = 0;
+        for (lower, upper) in (0..=axis).rev().zip(axis + 1..lines.len()) {
+            total += (lines[lower] ^ lines[upper]).count_ones();
+            if total > limit {
+                break;
             }
         }
-        None
+        total
+    }
+
+    /// All the mirror lines that hold once exactly `k` cells are changed
+    fn mirrors(&self, k: u32) -> Vec<Line> {
+        let (rows, cols) = self.masks();
+        let horizontal = (0..self.rows - 1)
+            .filter(|row| Self::differences(&rows, *row, k) == k)
+            .map(Line::Horizontal);
+        let vertical = (0..self.cols - 1)
+            .filter(|col| Self::differences(&cols, *col, k) == k)
+            .map(Line::Vertical);
+        horizontal.chain(vertical).collect()
+    }
+
+    fn find_mirror(&self, k: u32) -> Option<Line> {
+        self.mirrors(k).into_iter().next()
+    }
+
+    fn find_symmetry(&self) -> Option<Line> {
+        self.find_mirror(0)
     }
 
     fn fix_smudge(&self) -> Option<Line> {
-        let old_symmetry = self.find_symmetry().expect("Couldn't find a symmetry");
-        for row in 0..self.rows {
-            for col in 0..self.cols {
-                let mut new_pattern = self.elements.clone();
-                match new_pattern[row][col] {
-                    '.' => new_pattern[row][col] = '#',
-                    '#' => new_pattern[row][col] = '.',
-                    _ => unreachable!(),
-                }
-                let new_pattern = Pattern{ elements: new_pattern, rows: self.rows, cols: self.cols};
-                let new_symmetry = {
-                    for row in 0..self.rows - 1 {
-                        if new_pattern.is_symmetric(Line::Horizontal(row)) {
-                            if old_symmetry != Line::Horizontal(row) {
-                                return Some(Line::Horizontal(row));
-                            }
-                        }
-                    }
-                    for col in 0..self.cols - 1 {
-                        if new_pattern.is_symmetric(Line::Vertical(col)) {
-                            if old_symmetry != Line::Vertical(col) {
-                                return Some(Line::Vertical(col));
-                            }
-                        }
-                    }
-                    None
-                };
-                if new_symmetry.is_some() {
-                    return new_symmetry;
-                }
+        self.find_mirror(1)
+    }
+
+    /// The cells on the top or left side of the line that differ from
+    /// their reflection, as (row, col)
+    fn smudges(&self, line: &Line) -> Vec<(usize, usize)> {
+        let (rows, cols) = self.masks();
+        let (lines, axis) = match line {
+            Line::Horizontal(row) => (&rows, *row),
+            Line::Vertical(col) => (&cols, *col),
+        };
+        let mut smudges = vec![];
+        for (lower, upper) in (0..=axis).rev().zip(axis + 1..lines.len()) {
+            let mut diff = lines[lower] ^ lines[upper];
+            while diff != 0 {
+                let bit = diff.trailing_zeros() as usize;
+                smudges.push(match line {
+                    Line::Horizontal(_) => (lower, bit),
+                    Line::Vertical(_) => (bit, lower),
+                });
+                diff &= diff - 1;
             }
         }
-        None
+        smudges.sort();
+        smudges
     }
 }
 
@@ -129,33 +133,43 @@ fn parse(filename: &str) -> Vec<Pattern> {
     patterns
 }
 
+/// Sum the summaries of the mirror lines with exactly `k` smudges
+fn summarize(filename: &str, k: u32) -> usize {
+    parse(filename)
+        .iter()
+        .map(|pattern| {
+            pattern
+                .find_mirror(k)
+                .expect("Couldn't find a mirror line")
+                .summary()
+        })
+        .sum()
+}
+
 fn part_one(filename: &str) -> usize {
-    let patterns = parse(filename);
-    let mut total = 0;
-    for pattern in patterns {
-        total += match pattern.find_symmetry().expect("Couldn't find a symmetry") {
-            Line::Vertical(line) => line + 1,
-            Line::Horizontal(line) => 100 * line + 100,
-        };
-    }
-    total
+    summarize(filename, 0)
 }
 
 fn part_two(filename: &str) -> usize {
-    let patterns = parse(filename);
-    let mut total = 0;
-    for pattern in patterns {
-        total += match pattern.fix_smudge().expect("Couldn't find a smudge") {
-            Line::Vertical(line) => line + 1,
-            Line::Horizontal(line) => 100 * line + 100,
-        };
-    }
-    total
+    summarize(filename, 1)
 }
 
 fn main() {
     println!("Part one: {}", part_one("input.txt"));
     println!("Part two: {}", part_two("input.txt"));
+    if std::env::args().any(|arg| arg == "--smudges") {
+        for (ix, pattern) in parse("input.txt").iter().enumerate() {
+            let old = pattern.find_symmetry().expect("Couldn't find a symmetry");
+            let line = pattern.fix_smudge().expect("Couldn't find a smudge");
+            println!(
+                "Pattern {}: {:?} moves to {:?} with the smudge at {:?}",
+                ix,
+                old,
+                line,
+                pattern.smudges(&line)
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -178,10 +192,10 @@ mod tests {
                 vec!['#', '#', '.', '.', '.', '#', '.', '.', '.'],
                 vec!['.', '.', '.', '#', '#', '#', '.', '.', '.'],
                 vec!['#', '#', '#', '#', '#', '.', '.', '.', '.'],
-                vec!['#', '.', '.', '#', '#', '.', '.', '#', '#']
+                vec!['#', '.', '.', '#', '#', '.', '.', '#', '#'],
             ],
             rows: 13,
-            cols: 9
+            cols: 9,
         };
         let symmetry = pattern.fix_smudge().expect("Test failed");
         assert_eq!(symmetry, Line::Vertical(7));
@@ -197,15 +211,31 @@ mod tests {
                 vec!['#', '#', '#', '.', '.', '#', '.', '#', '#', '.', '.'],
                 vec!['.', '#', '#', '.', '.', '.', '.', '#', '#', '.', '.'],
                 vec!['.', '#', '#', '.', '.', '.', '.', '.', '#', '.', '.'],
-                vec!['#', '#', '#', '.', '.', '#', '.', '#', '#', '.', '.']
+                vec!['#', '#', '#', '.', '.', '#', '.', '#', '#', '.', '.'],
             ],
             rows: 7,
-            cols: 11
+            cols: 11,
         };
         let symmetry = pattern.find_symmetry().expect("Test failed");
         assert_eq!(symmetry, Line::Vertical(9));
         let new_symmetry = pattern.fix_smudge().expect("Test failed");
         assert_eq!(new_symmetry, Line::Horizontal(4));
+    }
 
+    #[test]
+    fn test_k_smudges() {
+        assert_eq!(part_one("test.txt"), 405);
+        assert_eq!(part_two("test.txt"), 400);
+        let patterns = parse("test.txt");
+        let line = patterns[0].fix_smudge().unwrap();
+        assert_eq!(line, Line::Horizontal(2));
+        assert_eq!(patterns[0].smudges(&line), vec![(0, 0)]);
+        let line = patterns[1].fix_smudge().unwrap();
+        assert_eq!(line, Line::Horizontal(0));
+        assert_eq!(patterns[1].smudges(&line), vec![(0, 4)]);
+        assert_eq!(patterns[0].smudges(&Line::Vertical(4)), vec![]);
+        // every line has some number of differences
+        let all: usize = (0..64).map(|k| patterns[0].mirrors(k).len()).sum();
+        assert_eq!(all, patterns[0].rows - 1 + patterns[0].cols - 1);
     }
-}
\ No newline at end of file
+}
@@ -2,188 +2,224 @@ use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    /// The step taken when moving this way as (row, col)
+    fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::South => (1, 0),
+            Direction::East => (0, 1),
+            Direction::West => (0, -1),
+        }
+    }
+}
+
+impl TryFrom<char> for Direction {
+    type Error = String;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'N' => Ok(Direction::North),
+            'S' => Ok(Direction::South),
+            'E' => Ok(Direction::East),
+            'W' => Ok(Direction::West),
+            other => Err(format!("Unrecognized direction: {}", other)),
+        }
+    }
+}
+
+/// A sequence of tilts such as `NWSE`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Program {
+    tilts: Vec<Direction>,
+}
+
+impl FromStr for Program {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tilts = s
+            .trim()
+            .chars()
+            .map(Direction::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { tilts })
+    }
+}
+
+/// Each row is a bitmask with bit `col` set for a rock in that column
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Board {
-    inner: Vec<Vec<char>>,
+    rocks: Vec<u128>,
+    cubes: Vec<u128>,
     rows: usize,
     cols: usize,
 }
 
 impl Display for Board {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut str = String::new();
         for row in 0..self.rows {
-            let r: String = self.inner[row].iter().cloned().collect();
-            str.push_str(&r);
-            str.push('\n');
+            for col in 0..self.cols {
+                let c = if self.rocks[row] & (1 << col) != 0 {
+                    'O'
+                } else if self.cubes[row] & (1 << col) != 0 {
+                    '#'
+                } else {
+                    '.'
+                };
+                write!(f, "{}", c)?;
+            }
+            writeln!(f)?;
         }
-        f.write_str(&str)
+        Ok(())
+    }
+}
+
+/// Move every bit one column in the direction of `dc`
+fn shift(mask: u128, dc: isize) -> u128 {
+    match dc {
+        1 => mask << 1,
+        -1 => mask >> 1,
+        _ => mask,
     }
 }
 
 impl Board {
-    fn tilt_north(&mut self) {
-        for row in 1..self.rows {
-            for col in 0..self.cols {
-                if self.inner[row][col] == 'O' {
-                    let mut fallen = false;
-                    for north in 1..=row {
-                        if self.inner[row - north][col] != '.' {
-                            self.inner[row][col] = '.';
-                            self.inner[row - north + 1][col] = 'O';
-                            fallen = true;
-                            break;
-                        }
-                    }
-                    if self.inner[0][col] == '.' && !fallen {
-                        self.inner[row][col] = '.';
-                        self.inner[0][col] = 'O';
-                    }
+    fn from_rows<S: AsRef<str>>(lines: &[S]) -> Self {
+        let cols = lines[0].as_ref().len();
+        assert!(cols <= 128, "The board is too wide");
+        let mut board = Board {
+            rocks: vec![0; lines.len()],
+            cubes: vec![0; lines.len()],
+            rows: lines.len(),
+            cols,
+        };
+        for (row, line) in lines.iter().enumerate() {
+            for (col, c) in line.as_ref().chars().enumerate() {
+                match c {
+                    'O' => board.rocks[row] |= 1 << col,
+                    '#' => board.cubes[row] |= 1 << col,
+                    _ => {}
                 }
             }
         }
+        board
     }
 
-    fn tilt_south(&mut self) {
-        for row in 0..self.rows - 1 {
-            let row = self.rows - 2 - row;
-            for col in 0..self.cols {
-                if self.inner[row][col] == 'O' {
-                    let mut fallen = false;
-                    for south in row + 1..self.rows {
-                        if self.inner[south][col] != '.' {
-                            self.inner[row][col] = '.';
-                            self.inner[south - 1][col] = 'O';
-                            fallen = true;
-                            break;
-                        }
-                    }
-                    if self.inner[self.rows - 1][col] == '.' && !fallen {
-                        self.inner[row][col] = '.';
-                        self.inner[self.rows - 1][col] = 'O';
-                    }
-                }
-            }
-        }
+    fn full(&self) -> u128 {
+        u128::MAX >> (128 - self.cols)
     }
 
-    fn tilt_east(&mut self) {
-        for col in 0..self.cols - 1 {
-            let col = self.cols - 2 - col;
+    /// Roll every rock as far as it goes. All rocks that can move take
+    /// a step at once until none of them can.
+    fn tilt(&mut self, direction: Direction) {
+        let (dr, dc) = direction.offset();
+        let full = self.full();
+        let mut moved = true;
+        while moved {
+            moved = false;
             for row in 0..self.rows {
-                if self.inner[row][col] == 'O' {
-                    let mut fallen = false;
-                    for east in col + 1..self.cols {
-                        if self.inner[row][east] != '.' {
-                            self.inner[row][col] = '.';
-                            self.inner[row][east -1] = 'O';
-                            fallen = true;
-                            break;
-                        }
-                    }
-                    if self.inner[row][self.cols - 1] == '.' && !fallen {
-                        self.inner[row][col] = '.';
-                        self.inner[row][self.cols - 1] = 'O';
-                    }
+                let Some(target) = row
+                    .checked_add_signed(dr)
+                    .filter(|target| *target < self.rows)
+                else {
+                    continue;
+                };
+                let free = full & !(self.rocks[target] | self.cubes[target]);
+                let movable = self.rocks[row] & shift(free, -dc);
+                if movable != 0 {
+                    self.rocks[row] &= !movable;
+                    self.rocks[target] |= shift(movable, dc);
+                    moved = true;
                 }
             }
         }
     }
 
-    fn tilt_west(&mut self) {
-        for col in 1..self.cols {
-            for row in 0..self.rows {
-                if self.inner[row][col] == 'O' {
-                    let mut fallen = false;
-                    for west in 1..=col {
-                        if self.inner[row][col - west] != '.' {
-                            self.inner[row][col] = '.';
-                            self.inner[row][col -west + 1] = 'O';
-                            fallen = true;
-                            break;
-                        }
-                    }
-                    if self.inner[row][0] == '.' && !fallen {
-                        self.inner[row][col] = '.';
-                        self.inner[row][0] = 'O';
-                    }
+    /// Run the program `repeats` times, skipping ahead once the board
+    /// comes back to an earlier state
+    fn run(&mut self, program: &Program, repeats: u64) {
+        let mut seen: HashMap<Vec<u128>, u64> = HashMap::new();
+        let mut ix = 0;
+        while ix < repeats {
+            if let Some(start) = seen.insert(self.rocks.clone(), ix) {
+                let remainder = (repeats - ix) % (ix - start);
+                for _ in 0..remainder {
+                    self.run_once(program);
                 }
+                return;
             }
+            self.run_once(program);
+            ix += 1;
         }
     }
 
-    fn north_load(&self) -> usize {
-        let mut total = 0;
-        for row in 0..self.rows {
-            for col in 0..self.cols {
-                if self.inner[row][col] == 'O' {
-                    total += self.rows - row;
-                }
-            }
+    fn run_once(&mut self, program: &Program) {
+        for direction in &program.tilts {
+            self.tilt(*direction);
         }
-        total
     }
 
-    fn spin_wash(&mut self) {
-        self.tilt_north();
-        self.tilt_west();
-        self.tilt_south();
-        self.tilt_east();
+    /// Each rock weighs its distance from the opposite edge, counting
+    /// the row or column at that edge as one
+    fn load(&self, edge: Direction) -> usize {
+        let mut total = 0;
+        for (row, mask) in self.rocks.iter().enumerate() {
+            total += match edge {
+                Direction::North => (self.rows - row) * mask.count_ones() as usize,
+                Direction::South => (row + 1) * mask.count_ones() as usize,
+                Direction::West | Direction::East => (0..self.cols)
+                    .filter(|col| mask & (1 << col) != 0)
+                    .map(|col| match edge {
+                        Direction::West => self.cols - col,
+                        _ => col + 1,
+                    })
+                    .sum(),
+            };
+        }
+        total
     }
 }
 
-
 fn parse(filename: &str) -> Board {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
-
-
-    let mut board = Board{inner: vec![], rows: 0, cols: 0};
+    let mut lines = vec![];
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
             break;
         }
-
-        let row = line.trim().chars().collect();
-        board.inner.push(row);
+        if !line.trim().is_empty() {
+            lines.push(line.trim().to_string());
+        }
         line.clear();
     }
-    board.rows = board.inner.len();
-    board.cols = board.inner[0].len();
-    board
+    Board::from_rows(&lines)
 }
 
-fn part_one(filename: &str)  {
-    let mut board= parse(filename);
-    board.tilt_north();
-    println!("Part one: {}", board.north_load());
+fn part_one(filename: &str) {
+    let mut board = parse(filename);
+    board.tilt(Direction::North);
+    println!("Part one: {}", board.load(Direction::North));
 }
 
 fn part_two(filename: &str) {
     let mut board = parse(filename);
-    let mut cache: HashMap<Board, usize> = HashMap::new();
-    let mut cycle_length = 0;
-    let mut cycle_start = 0;
-    for ix in 1..=1000000000 {
-        if !cache.contains_key(&board) {
-            let b = board.clone();
-            board.spin_wash();
-            cache.insert(b, ix);
-        } else {
-            let s = cache.get(&board).unwrap();
-            cycle_start = *s;
-            cycle_length = ix - s;
-            break;
-        }
-    }
-    let remainder = (1000000000 - cycle_start) % cycle_length;
-    for _ in 0..=remainder {
-        board.spin_wash()
-    }
-    println!("Part two: {}", board.north_load());
+    board.run(&Program::from_str("NWSE").unwrap(), 1_000_000_000);
+    println!("Part two: {}", board.load(Direction::North));
 }
+
 fn main() {
     part_one("input.txt");
     part_two("input.txt");
@@ -197,80 +233,87 @@ mod tests {
     fn test_cycle() {
         let mut board_original = parse("test.txt");
         let mut board = board_original.clone();
-        board.tilt_north();
-        let expected = Board {
-            inner: vec![
-                "OOOO.#.O..".chars().collect(),
-                "OO..#....#".chars().collect(),
-                "OO..O##..O".chars().collect(),
-                "O..#.OO...".chars().collect(),
-                "........#.".chars().collect(),
-                "..#....#.#".chars().collect(),
-                "..O..#.O.O".chars().collect(),
-                "..O.......".chars().collect(),
-                "#....###..".chars().collect(),
-                "#....#....".chars().collect(),
-            ],
-            rows: board.rows,
-            cols: board.cols,
-        };
+        board.tilt(Direction::North);
+        let expected = Board::from_rows(&[
+            "OOOO.#.O..",
+            "OO..#....#",
+            "OO..O##..O",
+            "O..#.OO...",
+            "........#.",
+            "..#....#.#",
+            "..O..#.O.O",
+            "..O.......",
+            "#....###..",
+            "#....#....",
+        ]);
         assert_eq!(board, expected);
-        board.tilt_west();
-        let expected = Board {
-            inner: vec![
-                "OOOO.#O...".chars().collect(),
-                "OO..#....#".chars().collect(),
-                "OOO..##O..".chars().collect(),
-                "O..#OO....".chars().collect(),
-                "........#.".chars().collect(),
-                "..#....#.#".chars().collect(),
-                "O....#OO..".chars().collect(),
-                "O.........".chars().collect(),
-                "#....###..".chars().collect(),
-                "#....#....".chars().collect(),
-            ],
-            rows: board.rows,
-            cols: board.cols,
-        };
+        board.tilt(Direction::West);
+        let expected = Board::from_rows(&[
+            "OOOO.#O...",
+            "OO..#....#",
+            "OOO..##O..",
+            "O..#OO....",
+            "........#.",
+            "..#....#.#",
+            "O....#OO..",
+            "O.........",
+            "#....###..",
+            "#....#....",
+        ]);
         assert_eq!(board, expected);
-        board.tilt_south();
-        let expected = Board {
-            inner: vec![
-                ".....#....".chars().collect(),
-                "....#.O..#".chars().collect(),
-                "O..O.##...".chars().collect(),
-                "O.O#......".chars().collect(),
-                "O.O....O#.".chars().collect(),
-                "O.#..O.#.#".chars().collect(),
-                "O....#....".chars().collect(),
-                "OO....OO..".chars().collect(),
-                "#O...###..".chars().collect(),
-                "#O..O#....".chars().collect(),
-            ],
-            rows: board.rows,
-            cols: board.cols,
-        };
+        board.tilt(Direction::South);
+        let expected = Board::from_rows(&[
+            ".....#....",
+            "....#.O..#",
+            "O..O.##...",
+            "O.O#......",
+            "O.O....O#.",
+            "O.#..O.#.#",
+            "O....#....",
+            "OO....OO..",
+            "#O...###..",
+            "#O..O#....",
+        ]);
         assert_eq!(board, expected);
-        board.tilt_east();
-        let expected = Board {
-            inner: vec![
-                ".....#....".chars().collect(),
-                "....#...O#".chars().collect(),
-                "...OO##...".chars().collect(),
-                ".OO#......".chars().collect(),
-                ".....OOO#.".chars().collect(),
-                ".O#...O#.#".chars().collect(),
-                "....O#....".chars().collect(),
-                "......OOOO".chars().collect(),
-                "#...O###..".chars().collect(),
-                "#..OO#....".chars().collect(),
-            ],
-            rows: board.rows,
-            cols: board.cols,
-        };
+        board.tilt(Direction::East);
+        let expected = Board::from_rows(&[
+            ".....#....",
+            "....#...O#",
+            "...OO##...",
+            ".OO#......",
+            ".....OOO#.",
+            ".O#...O#.#",
+            "....O#....",
+            "......OOOO",
+            "#...O###..",
+            "#..OO#....",
+        ]);
         assert_eq!(board, expected);
-        board_original.spin_wash();
+        board_original.run(&Program::from_str("NWSE").unwrap(), 1);
         assert_eq!(board_original, expected)
+    }
 
+    #[test]
+    fn test_program() {
+        let mut board = parse("test.txt");
+        let rocks = board.to_string().matches('O').count();
+        board.run(&Program::from_str("NWSE").unwrap(), 1_000_000_000);
+        assert_eq!(board.load(Direction::North), 64);
+        assert_eq!(board.to_string().matches('O').count(), rocks);
+
+        let mut board = parse("test.txt");
+        board.tilt(Direction::North);
+        assert_eq!(board.load(Direction::North), 136);
+        board.tilt(Direction::East);
+        let east = board.clone();
+        // tilting the same way twice changes nothing
+        board.tilt(Direction::East);
+        assert_eq!(board, east);
+        let total: usize = board.rocks.iter().map(|m| m.count_ones() as usize).sum();
+        assert_eq!(
+            board.load(Direction::East) + board.load(Direction::West),
+            total * (board.cols + 1)
+        );
+        assert!(Program::from_str("NWX").is_err());
     }
-}
\ No newline at end of file
+}
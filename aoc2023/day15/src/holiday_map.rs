@@ -0,0 +1,109 @@
+use super::*;
+use std::fmt::{Display, Formatter};
+
+const BOXES: usize = 256;
+
+/// The Holiday ASCII String Helper Manual Arrangement Procedure: keys
+/// are sorted into 256 boxes by their HASH, and each box keeps its
+/// entries in the order they were first inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HolidayMap<K, V> {
+    boxes: Vec<Vec<(K, V)>>,
+}
+
+/// A view into a single entry of the map
+pub enum Entry<'a, K, V> {
+    Occupied(&'a mut V),
+    /// The box the key belongs in and the key
+    Vacant(&'a mut Vec<(K, V)>, K),
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(value) => value,
+            Entry::Vacant(lenses, key) => {
+                lenses.push((key, default()));
+                &mut lenses.last_mut().unwrap().1
+            }
+        }
+    }
+}
+
+impl<K, V> Default for HolidayMap<K, V> {
+    fn default() -> Self {
+        Self {
+            boxes: (0..BOXES).map(|_| vec![]).collect(),
+        }
+    }
+}
+
+impl<K: AsRef<str>, V> HolidayMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn position(&self, key: &str) -> (usize, Option<usize>) {
+        let box_no = hash(key) as usize;
+        let slot = self.boxes[box_no]
+            .iter()
+            .position(|(k, _)| k.as_ref() == key);
+        (box_no, slot)
+    }
+
+    /// Replaces the value in place if the key is present, otherwise adds
+    /// it to the back of its box. Returns the old value.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(old) => Some(std::mem::replace(old, value)),
+            vacant => {
+                vacant.or_insert(value);
+                None
+            }
+        }
+    }
+
+    /// Takes the key out of its box, moving the entries behind it forward
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let (box_no, slot) = self.position(key);
+        slot.map(|slot| self.boxes[box_no].remove(slot).1)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        let (box_no, slot) = self.position(key);
+        slot.map(|slot| &self.boxes[box_no][slot].1)
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match self.position(key.as_ref()) {
+            (box_no, Some(slot)) => Entry::Occupied(&mut self.boxes[box_no][slot].1),
+            (box_no, None) => Entry::Vacant(&mut self.boxes[box_no], key),
+        }
+    }
+
+    /// The boxes that hold anything along with their numbers
+    pub fn boxes(&self) -> impl Iterator<Item = (usize, &[(K, V)])> {
+        self.boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, lenses)| !lenses.is_empty())
+            .map(|(box_no, lenses)| (box_no, lenses.as_slice()))
+    }
+}
+
+impl<K: AsRef<str>, V: Display> Display for HolidayMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (box_no, lenses) in self.boxes() {
+            write!(f, "Box {}:", box_no)?;
+            for (label, value) in lenses {
+                write!(f, " [{} {}]", label.as_ref(), value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
+mod holiday_map;
+
+use holiday_map::HolidayMap;
+
 fn parse(filename: &str) -> Vec<String> {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
@@ -13,7 +16,10 @@ fn parse(filename: &str) -> Vec<String> {
         if length == 0 {
             break;
         }
-        words = line.split(',').map(|s| s.trim().to_ascii_lowercase()).collect();
+        words = line
+            .split(',')
+            .map(|s| s.trim().to_ascii_lowercase())
+            .collect();
         line.clear();
     }
 
@@ -30,58 +36,60 @@ fn hash(word: &str) -> u64 {
     total
 }
 
-#[derive(Debug, Default)]
-struct HashishMap<'a> {
-    map: HashMap<u64, Vec<(&'a str, u64)>>
+/// A step of the initialization sequence
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Op<'a> {
+    /// `label-`
+    Remove(&'a str),
+    /// `label=value`
+    Insert(&'a str, u64),
 }
 
-
-impl<'a> HashishMap<'a> {
-    fn update(&mut self, cmd: &'a str) {
-        if cmd.contains('-') {
-            let mut cmd = cmd.split('-');
-            let label = cmd.next().unwrap();
-            let box_no = hash(label);
-            if let Some(bin) = self.map.get_mut(&box_no) {
-                bin.retain(|entry| entry.0 != label);
-            }
+impl<'a> Op<'a> {
+    fn parse(cmd: &'a str) -> Result<Self, String> {
+        if let Some(label) = cmd.strip_suffix('-') {
+            Ok(Op::Remove(label))
+        } else if let Some((label, value)) = cmd.split_once('=') {
+            let value = u64::from_str(value).map_err(|e| format!("{}: {}", cmd, e))?;
+            Ok(Op::Insert(label, value))
         } else {
-            let mut cmd = cmd.split('=');
-            let label = cmd.next().unwrap();
-            let value = u64::from_str(cmd.next().unwrap()).unwrap();
-            let box_no = hash(label);
-            if let Some(bin) = self.map.get_mut(&box_no) {
-                let mut updated = false;
-                for entry in bin.iter_mut() {
-                    if entry.0 == label {
-                        entry.1 = value;
-                        updated = true;
-                        break;
-                    }
-                }
-                if !updated {
-                    bin.push((label, value));
-                }
-            } else {
-                self.map.insert(box_no, vec![(label, value)]);
-            }
+            Err(format!("Unrecognized step: {}", cmd))
         }
     }
 
-    fn focal_power(&self) -> u64 {
-        let mut total = 0;
-        for (box_no, lens) in &self.map {
-            if lens.is_empty() {
-                continue;
+    fn apply(self, map: &mut HolidayMap<&'a str, u64>) {
+        match self {
+            Op::Remove(label) => {
+                map.remove(label);
+            }
+            Op::Insert(label, value) => {
+                map.insert(label, value);
             }
-            total += (box_no + 1) * lens.iter().enumerate()
-                .map(|(slot, (_, value))| (slot as u64 + 1) * (*value))
-                .sum::<u64>();
         }
-        total
     }
 }
 
+fn focal_power(map: &HolidayMap<&str, u64>) -> u64 {
+    map.boxes()
+        .map(|(box_no, lenses)| {
+            (box_no as u64 + 1)
+                * lenses
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, (_, value))| (slot as u64 + 1) * value)
+                    .sum::<u64>()
+        })
+        .sum()
+}
+
+fn initialize(words: &[String]) -> HolidayMap<&str, u64> {
+    let mut map = HolidayMap::new();
+    for word in words {
+        Op::parse(word).unwrap().apply(&mut map);
+    }
+    map
+}
+
 fn part_one(filename: &str) {
     let words = parse(filename);
     let res = words.iter().map(|w| hash(w)).sum::<u64>();
@@ -90,15 +98,99 @@ fn part_one(filename: &str) {
 
 fn part_two(filename: &str) {
     let words = parse(filename);
-    let mut map = HashishMap::default();
-    for word in &words {
-        map.update(&word);
+    let map = initialize(&words);
+    println!("Part two: {}", focal_power(&map));
+    if std::env::args().any(|arg| arg == "--boxes") {
+        print!("{}", map);
+    }
+    if let Some(label) = std::env::args().skip_while(|arg| arg != "--lens").nth(1) {
+        match map.get(label.as_str()) {
+            Some(value) => println!("{}: focal length {} in box {}", label, value, hash(&label)),
+            None => println!("{} is in no box", label),
+        }
     }
-    println!("Part two: {}", map.focal_power());
 }
 
 fn main() {
     part_one("input.txt");
     part_two("input.txt");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_initialize() {
+        assert_eq!(hash("HASH"), 52);
+        let words = parse("test.txt");
+        let map = initialize(&words);
+        assert_eq!(
+            map.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n"
+        );
+        assert_eq!(focal_power(&map), 145);
+        assert_eq!(map.get("ot"), Some(&7));
+        assert_eq!(map.get("qp"), None);
+        assert!(Op::parse("qp=x").is_err());
+        assert_eq!(Op::parse("qp"), Err("Unrecognized step: qp".to_string()));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map: HolidayMap<String, Vec<u8>> = HolidayMap::new();
+        map.entry("rn".to_string()).or_insert_with(Vec::new).push(1);
+        map.entry("rn".to_string()).or_insert_with(Vec::new).push(2);
+        *map.entry("cm".to_string()).or_insert(vec![]) = vec![3];
+        assert_eq!(map.get("rn"), Some(&vec![1, 2]));
+        // both land in box 0, in the order they were first inserted
+        assert_eq!(map.boxes().count(), 1);
+        let (box_no, lenses) = map.boxes().next().unwrap();
+        let labels: Vec<_> = lenses.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!((box_no, labels), (0, vec!["rn", "cm"]));
+        map.entry("cm".to_string()).or_insert(vec![]).push(4);
+        assert_eq!(map.insert("cm".to_string(), vec![5]), Some(vec![3, 4]));
+        assert_eq!(map.remove("cm"), Some(vec![5]));
+        assert_eq!(map.get("cm"), None);
+    }
+
+    fn ops() -> impl Strategy<Value = Vec<(String, Option<u64>)>> {
+        prop::collection::vec(("[a-d]{1,2}", prop::option::of(1u64..10)), 0..200)
+    }
 
+    proptest! {
+        #[test]
+        fn behaves_like_hash_map(ops in ops()) {
+            let mut map = HolidayMap::new();
+            let mut reference = HashMap::new();
+            for (key, value) in &ops {
+                match value {
+                    Some(value) => {
+                        prop_assert_eq!(
+                            map.insert(key.clone(), *value),
+                            reference.insert(key.clone(), *value)
+                        );
+                    }
+                    None => prop_assert_eq!(map.remove(key), reference.remove(key)),
+                }
+            }
+            for (key, value) in &reference {
+                prop_assert_eq!(map.get(key), Some(value));
+            }
+            let mut entries: Vec<_> = map
+                .boxes()
+                .flat_map(|(_, lenses)| lenses.iter().map(|(k, v)| (k.clone(), *v)))
+                .collect();
+            entries.sort();
+            let mut expected: Vec<_> = reference.into_iter().collect();
+            expected.sort();
+            prop_assert_eq!(entries, expected);
+            // every key sits in the box given by its hash
+            for (box_no, lenses) in map.boxes() {
+                prop_assert!(lenses.iter().all(|(k, _)| hash(k) as usize == box_no));
+            }
+        }
+    }
 }
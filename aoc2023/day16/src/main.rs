@@ -0,0 +1,235 @@
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+type Pos = (usize, usize);
+
+/// The direction a beam is heading in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Dir {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Dir {
+    /// The bit for this direction in a tile's set of visiting beams
+    fn bit(&self) -> u8 {
+        1 << (*self as u8)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TileType {
+    Empty,
+    /// `/`
+    Forward,
+    /// `\`
+    Back,
+    /// `|`
+    Vertical,
+    /// `-`
+    Horizontal,
+}
+
+impl TileType {
+    /// Given a beam entering this tile heading in `dir`, find the (up to)
+    /// two directions it leaves in
+    fn exits(&self, dir: Dir) -> [Option<Dir>; 2] {
+        match (self, dir) {
+            (TileType::Forward, Dir::North) | (TileType::Back, Dir::South) => {
+                [Some(Dir::East), None]
+            }
+            (TileType::Forward, Dir::South) | (TileType::Back, Dir::North) => {
+                [Some(Dir::West), None]
+            }
+            (TileType::Forward, Dir::East) | (TileType::Back, Dir::West) => {
+                [Some(Dir::North), None]
+            }
+            (TileType::Forward, Dir::West) | (TileType::Back, Dir::East) => {
+                [Some(Dir::South), None]
+            }
+            (TileType::Vertical, Dir::East | Dir::West) => [Some(Dir::North), Some(Dir::South)],
+            (TileType::Horizontal, Dir::North | Dir::South) => [Some(Dir::East), Some(Dir::West)],
+            _ => [Some(dir), None],
+        }
+    }
+}
+
+impl From<char> for TileType {
+    fn from(value: char) -> Self {
+        match value {
+            '.' => TileType::Empty,
+            '/' => TileType::Forward,
+            '\\' => TileType::Back,
+            '|' => TileType::Vertical,
+            '-' => TileType::Horizontal,
+            other => panic!("Unexpected character {}", other),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Grid {
+    grid: Vec<Vec<TileType>>,
+    rows: usize,
+    cols: usize,
+}
+
+impl Grid {
+    /// The next position in a direction, if it is in the grid
+    fn step(&self, pos: Pos, dir: Dir) -> Option<Pos> {
+        let next = match dir {
+            Dir::North => (pos.0.checked_sub(1)?, pos.1),
+            Dir::South => (pos.0 + 1, pos.1),
+            Dir::East => (pos.0, pos.1 + 1),
+            Dir::West => (pos.0, pos.1.checked_sub(1)?),
+        };
+        (next.0 < self.rows && next.1 < self.cols).then_some(next)
+    }
+
+    /// Follow a beam entering the tile at `pos` heading in `dir`. Each tile
+    /// records the directions of the beams that passed through it, so a
+    /// beam caught in a loop stops once it repeats itself.
+    fn beam(&self, pos: Pos, dir: Dir) -> Vec<Vec<u8>> {
+        let mut visited = vec![vec![0u8; self.cols]; self.rows];
+        let mut beams = vec![(pos, dir)];
+        while let Some(((row, col), dir)) = beams.pop() {
+            if visited[row][col] & dir.bit() != 0 {
+                continue;
+            }
+            visited[row][col] |= dir.bit();
+            for exit in self.grid[row][col].exits(dir).into_iter().flatten() {
+                if let Some(next) = self.step((row, col), exit) {
+                    beams.push((next, exit));
+                }
+            }
+        }
+        visited
+    }
+
+    /// The number of tiles a beam entering at `pos` heading in `dir` passes
+    /// through
+    fn energized(&self, pos: Pos, dir: Dir) -> usize {
+        self.beam(pos, dir)
+            .iter()
+            .flatten()
+            .filter(|dirs| **dirs != 0)
+            .count()
+    }
+
+    /// Every way of shining a beam into the grid from its edges
+    fn entries(&self) -> Vec<(Pos, Dir)> {
+        let mut entries = vec![];
+        for row in 0..self.rows {
+            entries.push(((row, 0), Dir::East));
+            entries.push(((row, self.cols - 1), Dir::West));
+        }
+        for col in 0..self.cols {
+            entries.push(((0, col), Dir::South));
+            entries.push(((self.rows - 1, col), Dir::North));
+        }
+        entries
+    }
+
+    /// The entry that energizes the most tiles, along with that count
+    fn best(&self) -> Option<((Pos, Dir), usize)> {
+        self.entries()
+            .into_par_iter()
+            .map(|(pos, dir)| ((pos, dir), self.energized(pos, dir)))
+            .max_by_key(|(_, count)| *count)
+    }
+
+    fn render(&self, visited: &[Vec<u8>]) -> String {
+        visited
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|dirs| if *dirs != 0 { '#' } else { '.' })
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    }
+}
+
+fn parse_file(filename: &str) -> Grid {
+    let file = File::open(filename).unwrap();
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    let mut grid = vec![];
+    while let Ok(length) = reader.read_line(&mut line) {
+        if length == 0 {
+            break;
+        }
+        if !line.trim().is_empty() {
+            grid.push(line.trim().chars().map(TileType::from).collect::<Vec<_>>());
+        }
+        line.clear()
+    }
+    let cols = grid[0].len();
+    Grid {
+        rows: grid.len(),
+        cols,
+        grid,
+    }
+}
+
+fn part_one(filename: &str) {
+    let grid = parse_file(filename);
+    println!("Part one: {}", grid.energized((0, 0), Dir::East));
+    if std::env::args().any(|arg| arg == "--render") {
+        print!("{}", grid.render(&grid.beam((0, 0), Dir::East)));
+    }
+}
+
+fn part_two(filename: &str) {
+    let grid = parse_file(filename);
+    let ((pos, dir), count) = grid.best().unwrap();
+    println!("Part two: {} (entering {:?} at {:?})", count, dir, pos);
+}
+
+fn main() {
+    part_one("input.txt");
+    part_two("input.txt")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beam() {
+        let grid = parse_file("test.txt");
+        assert_eq!(grid.energized((0, 0), Dir::East), 46);
+        assert_eq!(
+            grid.render(&grid.beam((0, 0), Dir::East)),
+            "######....\n.#...#....\n.#...#####\n.#...##...\n.#...##...\n\
+             .#...##...\n.#..####..\n########..\n.#######..\n.#...#.#..\n"
+        );
+        assert_eq!(grid.entries().len(), 40);
+        assert_eq!(grid.best(), Some((((0, 3), Dir::South), 51)));
+    }
+
+    #[test]
+    fn test_loop() {
+        // the beam splits on the left and would run round the edge forever
+        let grid = Grid {
+            grid: ["/-\\", "|.|", "\\-/"]
+                .iter()
+                .map(|row| row.chars().map(TileType::from).collect())
+                .collect(),
+            rows: 3,
+            cols: 3,
+        };
+        let visited = grid.beam((1, 0), Dir::East);
+        assert_eq!(grid.render(&visited), "###\n#.#\n###\n");
+        // the halves go round opposite ways and both come back to the start
+        assert_eq!(
+            visited[1][0],
+            Dir::North.bit() | Dir::South.bit() | Dir::East.bit()
+        );
+        assert_eq!(TileType::Back.exits(Dir::West), [Some(Dir::North), None]);
+    }
+}
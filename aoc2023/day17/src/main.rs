@@ -1,11 +1,11 @@
-use std::cell::RefCell;
-use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::rc::Rc;
 use std::str::FromStr;
 
+type Pos = (usize, usize);
+
 #[derive(Debug)]
 struct Board {
     inner: Vec<Vec<u64>>,
@@ -14,125 +14,175 @@ struct Board {
 }
 
 impl Board {
-    fn at(&self, (row, col): (usize, usize)) -> Option<u64> {
+    fn at(&self, (row, col): Pos) -> Option<u64> {
         if row >= self.rows || col >= self.cols {
             None
         } else {
             Some(self.inner[row][col])
         }
     }
-}
 
+    /// The next position in a direction, if it is on the board
+    fn step(&self, (row, col): Pos, dir: Dir) -> Option<Pos> {
+        let next = match dir {
+            Dir::Up => (row.checked_sub(1)?, col),
+            Dir::Down => (row + 1, col),
+            Dir::Left => (row, col.checked_sub(1)?),
+            Dir::Right => (row, col + 1),
+        };
+        self.at(next).map(|_| next)
+    }
+}
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 enum Dir {
-    Up, Down, Left, Right
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Dir {
+    fn turns(&self) -> [Dir; 2] {
+        match self {
+            Dir::Up | Dir::Down => [Dir::Left, Dir::Right],
+            Dir::Left | Dir::Right => [Dir::Up, Dir::Down],
+        }
+    }
+
+    fn arrow(&self) -> char {
+        match self {
+            Dir::Up => '^',
+            Dir::Down => 'v',
+            Dir::Left => '<',
+            Dir::Right => '>',
+        }
+    }
 }
 
+const DIRS: [Dir; 4] = [Dir::Up, Dir::Down, Dir::Left, Dir::Right];
+
+/// How a crucible moves: it has to go at least `min_run` blocks in a
+/// straight line before turning or stopping, and at most `max_run`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Crucible {
+    min_run: usize,
+    max_run: usize,
+}
 
+/// The blocks entered on the way to the factory and the heat lost
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Partial {
-    head: (usize, usize),
-    dir: Dir,
+struct Path {
     heat_loss: u64,
-    length: i8,
-    best: Rc<RefCell<Option<u64>>>,
+    steps: Vec<(Pos, Dir)>,
 }
 
-impl Partial {
+impl Path {
+    /// The board with each block on the path replaced by the direction
+    /// it was entered in
+    fn draw(&self, board: &Board) -> String {
+        let mut drawing: Vec<Vec<char>> = board
+            .inner
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|heat| char::from_digit(*heat as u32, 10).unwrap())
+                    .collect()
+            })
+            .collect();
+        for ((row, col), dir) in &self.steps {
+            drawing[*row][*col] = dir.arrow();
+        }
+        drawing
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect()
+    }
+}
 
-    fn update_best(&self) {
-        let current = self.best.borrow_mut().unwrap_or(u64::MAX);
-        *self.best.borrow_mut() = Some(std::cmp::min(self.heat_loss, current));
+impl Crucible {
+    fn normal() -> Self {
+        Self {
+            min_run: 1,
+            max_run: 3,
+        }
     }
-    fn neighbors(&self, board: &Board) -> impl Iterator<Item=Self> {
-        let mut ns = [const { None }; 4];
-        ns[2] = if self.head.0 != 0 && self.dir != Dir::Down {
-            // Go up
-            let next_head = (self.head.0 - 1, self.head.1);
-            let heat_loss = board.at(next_head).unwrap();
-            let mut next = self.clone();
-            next.head = next_head;
-            next.dir = Dir::Up;
-            next.heat_loss += heat_loss;
-            if self.dir == next.dir {
-                next.length += 1;
-            } else {
-                next.length = 1;
-            }
-            if next.length > 3  {
-                None
-            } else {
-                Some(next)
-            }
-        } else {
-            None
-        };
-        ns[3] = if self.head.1 != 0 && self.dir != Dir::Right {
-            // Go left
-            let next_head = (self.head.0, self.head.1 - 1);
-            let heat_loss = board.at(next_head).unwrap();
-            let mut next = self.clone();
-            next.head = next_head;
-            next.dir = Dir::Left;
-            next.heat_loss += heat_loss;
-            if self.dir == next.dir {
-                next.length += 1;
-            } else {
-                next.length = 1;
-            }
-            if next.length > 3  {
-                None
-            } else {
-                Some(next)
-            }
-        }  else {
-            None
+
+    fn ultra() -> Self {
+        Self {
+            min_run: 4,
+            max_run: 10,
+        }
+    }
+
+    /// Dijkstra over the states (position, direction, length of the
+    /// current run), starting out both right and down from the top left.
+    fn solve(&self, board: &Board) -> Option<Path> {
+        let end = (board.rows - 1, board.cols - 1);
+        if end == (0, 0) {
+            return Some(Path {
+                heat_loss: 0,
+                steps: vec![],
+            });
+        }
+        let runs = self.max_run + 1;
+        let index = |(row, col): Pos, dir: Dir, run: usize| {
+            ((row * board.cols + col) * 4 + dir as usize) * runs + run
         };
-        ns[0] = if self.dir == Dir::Up  {
-            None
-        } else if let Some(heat_loss) = board.at((self.head.0 + 1, self.head.1)) {
-            // Go down
-            let mut next = self.clone();
-            next.head = (self.head.0 + 1, self.head.1);
-            next.dir = Dir::Down;
-            next.heat_loss += heat_loss;
-            if self.dir == next.dir {
-                next.length += 1;
-            } else {
-                next.length = 1;
+        let states = board.rows * board.cols * 4 * runs;
+        let mut dist = vec![u64::MAX; states];
+        let mut parent: Vec<Option<usize>> = vec![None; states];
+        let mut queue = BinaryHeap::new();
+        for dir in [Dir::Right, Dir::Down] {
+            if let Some(next) = board.step((0, 0), dir) {
+                let ix = index(next, dir, 1);
+                dist[ix] = board.at(next).unwrap();
+                queue.push(Reverse((dist[ix], next, dir, 1)));
             }
-            if next.length > 3   {
-                None
-            } else {
-                Some(next)
+        }
+
+        while let Some(Reverse((heat_loss, pos, dir, run))) = queue.pop() {
+            let current = index(pos, dir, run);
+            if heat_loss > dist[current] {
+                continue;
             }
-        } else {
-            None
-        };
-        ns[1] = if self.dir == Dir::Left {
-            None
-        } else if let Some(heat_loss) = board.at((self.head.0, self.head.1 + 1)) {
-            // Go right
-            let mut next = self.clone();
-            next.head = (self.head.0, self.head.1 + 1);
-            next.dir = Dir::Right;
-            next.heat_loss += heat_loss;
-            if self.dir == next.dir {
-                next.length += 1;
-            } else {
-                next.length = 1;
+            if pos == end && run >= self.min_run {
+                return Some(self.path(heat_loss, current, &parent, board));
             }
-            if next.length > 3  {
-                None
-            } else {
-                Some(next)
+            let straight = (run < self.max_run).then_some((dir, run + 1));
+            let turns = dir
+                .turns()
+                .map(|turn| (turn, 1))
+                .into_iter()
+                .filter(|_| run >= self.min_run);
+            for (next_dir, next_run) in straight.into_iter().chain(turns) {
+                let Some(next) = board.step(pos, next_dir) else {
+                    continue;
+                };
+                let ix = index(next, next_dir, next_run);
+                let next_loss = heat_loss + board.at(next).unwrap();
+                if next_loss < dist[ix] {
+                    dist[ix] = next_loss;
+                    parent[ix] = Some(current);
+                    queue.push(Reverse((next_loss, next, next_dir, next_run)));
+                }
             }
-        } else {
-            None
-        };
+        }
+        None
+    }
 
-        ns.into_iter().filter_map(|x| x)
+    fn path(&self, heat_loss: u64, state: usize, parent: &[Option<usize>], board: &Board) -> Path {
+        let runs = self.max_run + 1;
+        let mut steps = vec![];
+        let mut state = Some(state);
+        while let Some(ix) = state {
+            let cell = ix / runs / 4;
+            let dir = DIRS[ix / runs % 4];
+            steps.push(((cell / board.cols, cell % board.cols), dir));
+            state = parent[ix];
+        }
+        steps.reverse();
+        Path { heat_loss, steps }
     }
 }
 
@@ -147,12 +197,15 @@ fn parse(filename: &str) -> Board {
         if length == 0 {
             break;
         }
-        let row = line.trim()
-            .chars()
-            .map(|c| u64::from_str(&c.to_string()).unwrap() )
-            .collect::<Vec<_>>();
-        cols = row.len();
-        board.push(row);
+        if !line.trim().is_empty() {
+            let row = line
+                .trim()
+                .chars()
+                .map(|c| u64::from_str(&c.to_string()).unwrap())
+                .collect::<Vec<_>>();
+            cols = row.len();
+            board.push(row);
+        }
         line.clear()
     }
     Board {
@@ -162,103 +215,28 @@ fn parse(filename: &str) -> Board {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Return {
-    BadPartial,
-    BestDist(u64),
-}
-
-impl PartialOrd for Return {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Return::BadPartial, Return::BestDist(_)) => Some(Ordering::Greater),
-            (Return::BestDist(_), Return::BadPartial) => Some(Ordering::Less),
-            (Return::BestDist(a), Return::BestDist(b)) => a.partial_cmp(b),
-            _ => Some(Ordering::Equal)
-        }
-    }
-}
-
-impl Ord for Return {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+fn run(filename: &str, crucible: Crucible) -> Path {
+    let board = parse(filename);
+    let path = crucible
+        .solve(&board)
+        .expect("The factory cannot be reached");
+    if std::env::args().any(|arg| arg == "--path") {
+        print!("{}", path.draw(&board));
     }
+    path
 }
 
-fn find_path(
-    mut partial: Partial,
-    board: &Board,
-    cache: &mut HashMap<((usize, usize), Dir, i8), u64>,
-) -> Return {
-    let heat_loss = board.at(partial.head).unwrap();
-    if partial.head == (1, 4) && partial.dir == Dir::Right && partial.length == 2 {
-        println!("HEY");
-    }
-    if partial.heat_loss > partial.best.borrow().unwrap_or(u64::MAX) {
-        return Return::BadPartial;
-    }
-
-    if partial.head == (board.rows - 1, board.cols - 1) {
-        partial.update_best();
-        return Return::BestDist(heat_loss)
-    }
-    if let Some(x) = cache.get(&(partial.head, partial.dir, partial.length)) {
-        partial.heat_loss = (partial.heat_loss - heat_loss).saturating_add(*x);
-        partial.update_best();
-        return Return::BestDist(*x);
-    }
-
-    let res = partial.neighbors(board)
-        .map(|next|{
-            find_path(next, board, cache)
-        })
-        .min()
-        .unwrap_or(Return::BadPartial);
-
-    if let Return::BestDist(d) = res {
-        cache.insert((partial.head, partial.dir, partial.length), d.saturating_add(heat_loss));
-        Return::BestDist(d.saturating_add(heat_loss))
-    } else {
-        Return::BadPartial
-    }
-
+fn part_one(filename: &str) {
+    println!("Part one: {}", run(filename, Crucible::normal()).heat_loss);
 }
 
-fn part_one(filename: &str) {
-    let board = parse(filename);
-    let mut cache = HashMap::new();
-    let best = Rc::new(RefCell::new(None));
-    let inits = [
-        Partial {
-            head: (0, 0),
-            dir: Dir::Right,
-            heat_loss: 0,
-            length: 1,
-            best: best.clone(),
-        },
-       /* Partial {
-            head: (0, 0),
-            dir: Dir::Down,
-            heat_loss: 0,
-            length: 1,
-            best: best.clone(),
-        },*/
-    ];
-    let Return::BestDist(min_heat) = inits
-        .into_iter()
-        .map(|p| find_path(p, &board, &mut cache))
-        .min()
-        .unwrap() else {
-        unreachable!()
-    };
-    for e in cache {
-        println!("{:?}", e);
-    }
-    println!("Part one: {:?}", min_heat);
+fn part_two(filename: &str) {
+    println!("Part two: {}", run(filename, Crucible::ultra()).heat_loss);
 }
 
 fn main() {
-    part_one("test.txt");
+    part_one("input.txt");
+    part_two("input.txt");
 }
 
 #[cfg(test)]
@@ -272,94 +250,78 @@ mod tests {
             rows: 2,
             cols: 3,
         };
-        let mut cache = HashMap::new();
-        let best = Rc::new(RefCell::new(None));
-        let inits = [
-            Partial {
-                head: (0, 0),
-                dir: Dir::Right,
-                heat_loss: 2,
-                length: 1,
-                best: best.clone(),
-            },
-            Partial {
-                head: (0, 0),
-                dir: Dir::Down,
-                heat_loss: 2,
-                length: 1,
-                best: best.clone(),
-            },
-        ];
-        let min_heat = inits
-            .into_iter()
-            .map(|p| find_path(p, &board, &mut cache))
-            .min()
-            .unwrap();
-        assert_eq!(min_heat, Return::BestDist(6));
+        let path = Crucible::normal().solve(&board).unwrap();
+        assert_eq!(path.heat_loss, 4);
+        assert_eq!(path.draw(&board), "2>4\n3v>\n");
     }
 
     #[test]
     fn basic2() {
         let board = Board {
-            inner: vec![vec![5, 3], vec![8, 7], vec![5, 3], vec![6, 3], vec![3, 5], vec![3, 3]],
+            inner: vec![
+                vec![5, 3],
+                vec![8, 7],
+                vec![5, 3],
+                vec![6, 3],
+                vec![3, 5],
+                vec![3, 3],
+            ],
             rows: 6,
             cols: 2,
         };
-        let mut cache = HashMap::new();
-        let best = Rc::new(RefCell::new(None));
-        let inits = [
-            Partial {
-                head: (0, 0),
-                dir: Dir::Right,
-                heat_loss: 5,
-                length: 1,
-                best: best.clone(),
-            },
-            Partial {
-                head: (0, 0),
-                dir: Dir::Down,
-                heat_loss: 5,
-                length: 1,
-                best: best.clone(),
-            },
-        ];
-        let min_heat = inits
-            .into_iter()
-            .map(|p| find_path(p, &board, &mut cache))
-            .min()
-            .unwrap();
-        assert_eq!(min_heat, Return::BestDist(32));
+        let path = Crucible::normal().solve(&board).unwrap();
+        assert_eq!(path.heat_loss, 27);
     }
+
     #[test]
     fn basic3() {
         let board = Board {
-            inner: vec![vec![2, 4, 1, 3, 4, 3, 2, 3, 1], vec![3, 2, 1, 5, 4, 5, 3, 5, 3]],
+            inner: vec![
+                vec![2, 4, 1, 3, 4, 3, 2, 3, 1],
+                vec![3, 2, 1, 5, 4, 5, 3, 5, 3],
+            ],
             rows: 2,
             cols: 9,
         };
-        let mut cache = HashMap::new();
-        let best = Rc::new(RefCell::new(None));
-        let inits = [
-            Partial {
-                head: (0, 0),
-                dir: Dir::Right,
-                heat_loss: 2,
-                length: 1,
-                best: best.clone(),
-            },
-            Partial {
-                head: (0, 0),
-                dir: Dir::Down,
-                heat_loss: 2,
-                length: 1,
-                best: best.clone(),
-            },
-        ];
-        let min_heat = inits
-            .into_iter()
-            .map(|p| find_path(p, &board, &mut cache))
-            .min()
-            .unwrap();
-        assert_eq!(min_heat, Return::BestDist(34));
+        let path = Crucible::normal().solve(&board).unwrap();
+        assert_eq!(path.heat_loss, 32);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_crucibles() {
+        let board = parse("test.txt");
+        let path = Crucible::normal().solve(&board).unwrap();
+        assert_eq!(path.heat_loss, 102);
+        let total: u64 = path
+            .steps
+            .iter()
+            .map(|(pos, _)| board.at(*pos).unwrap())
+            .sum();
+        assert_eq!(total, 102);
+        assert_eq!(Crucible::ultra().solve(&board).unwrap().heat_loss, 94);
+
+        let board = Board {
+            inner: vec![
+                vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+                vec![9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1],
+                vec![9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1],
+                vec![9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1],
+                vec![9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1],
+            ],
+            rows: 5,
+            cols: 12,
+        };
+        let path = Crucible::ultra().solve(&board).unwrap();
+        assert_eq!(path.heat_loss, 71);
+        // no run is shorter than four blocks, including the last one
+        let mut runs = vec![1];
+        for pair in path.steps.windows(2) {
+            if pair[0].1 == pair[1].1 {
+                *runs.last_mut().unwrap() += 1;
+            } else {
+                runs.push(1);
+            }
+        }
+        assert!(runs.iter().all(|run| (4..=10).contains(run)));
+    }
+}
@@ -1,233 +1,127 @@
-use std::cmp::min;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
-use itertools::Itertools;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Dir {
-    R, D, L, U
+    R,
+    D,
+    L,
+    U,
 }
 
-/// Indicates if a vertex in a simple rectilinear
-/// polygon is concave/convex. An edge case occurs
-/// if two moves travel in the same direction, represented
-/// by `Internal`.
+/// How a line of the dig plan is read
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Conv {
-    Convex,
-    Concave,
-    Internal,
+enum Encoding {
+    /// The direction and amount written out, `R 6`
+    Plain,
+    /// The first five hex digits of the colour are the amount and the
+    /// last is the direction
+    Hex,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Move {
     dir: Dir,
     amount: i64,
-    hex: String,
-}
-
-/// The data of a continuator rectangle to be removed.
-#[derive(Debug, Clone)]
-struct Continuator {
-    ix: usize,
-    edge: [(i64, i64, Conv); 2],
-    length: i64,
-    prev_ix: usize,
-    next_ix: usize,
-    prev: (i64, i64, Conv),
-    next: (i64, i64, Conv),
-    prev_length: i64,
-    next_length: i64,
-}
-
-
-struct Tunnel {
-    boundary: Vec<(i64, i64, Conv)>,
+    /// The colour the trench is painted, `#70c710`
+    colour: String,
 }
 
-fn parse_hex(hex: &str) -> Move {
-    let mut hex = hex.replace('#', "");
-    let dir = hex.pop().unwrap();
+fn parse_hex(hex: &str) -> (Dir, i64) {
+    let hex = hex.trim_start_matches('#');
+    let (amount, dir) = hex.split_at(hex.len() - 1);
     let dir = match dir {
-        '0' => Dir::R,
-        '1' => Dir::D,
-        '2' => Dir::L,
-        '3' => Dir::U,
+        "0" => Dir::R,
+        "1" => Dir::D,
+        "2" => Dir::L,
+        "3" => Dir::U,
         _ => unreachable!(),
     };
-    let amount = i64::from_str_radix(&hex, 16).unwrap();
-    Move {
-        dir,
-        amount,
-        hex: "".to_string(),
-    }
+    (dir, i64::from_str_radix(amount, 16).unwrap())
 }
 
-impl Tunnel {
-    fn find_boundary(moves: &[Move]) -> Self {
-        let last_dir = moves.last().unwrap().dir;
-        // The boundary is traversed in a clockwise manner. This allows us to easily
-        // classify vertices as concave/convex
-        let convexity = |first: Dir, second: Dir| match (first, second) {
-            (Dir::U, Dir::R) | (Dir::D, Dir::L) | (Dir::R, Dir::D) | (Dir::L, Dir::U) => Conv::Convex,
-            (Dir::U, Dir::U) | (Dir::D, Dir::D) | (Dir::R, Dir::R) | (Dir::L, Dir::L) => Conv::Internal,
-            _ => Conv::Concave
-        };
-        let mut boundary = vec![];
-        let mut last = (0i64, 0, last_dir);
+/// The trench as a closed rectilinear polygon through the centres of the
+/// dug cubes
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Polygon {
+    vertices: Vec<(i64, i64)>,
+}
 
+impl Polygon {
+    fn from_moves(moves: &[Move]) -> Self {
+        let mut vertices = vec![(0, 0)];
+        let (mut row, mut col) = (0, 0);
         for m in moves {
-            let next = match m.dir {
-                Dir::R => (last.0, last.1 + m.amount),
-                Dir::D => (last.0 + m.amount, last.1),
-                Dir::L => (last.0, last.1 - m.amount),
-                Dir::U => (last.0 - m.amount, last.1),
-            };
-            let convex = convexity(last.2, m.dir);
-            boundary.push((last.0, last.1, convex));
-            last = (next.0, next.1, m.dir);
-        }
-        Self {
-            boundary,
+            match m.dir {
+                Dir::R => col += m.amount,
+                Dir::D => row += m.amount,
+                Dir::L => col -= m.amount,
+                Dir::U => row -= m.amount,
+            }
+            vertices.push((row, col));
         }
+        assert_eq!(vertices.pop(), Some((0, 0)), "The dig plan is not closed");
+        Self { vertices }
     }
 
-    /// Find and delete a continuator rectangle, returning the number of
-    /// points contained in it.
-    ///
-    /// First, find the smallest knob in the polygon, it must belong to a side
-    /// of a continuator rectangle.
-    ///
-    /// Look at the two adjacent edges and determine if the edge can be pushed
-    /// inward. If so, return the volume of points removed by this shrinking.
-    ///
-    /// The last case is when only a rectangle is left. Then return the area.
-    fn next_continuator(&mut self) -> u64 {
-        // find the smallest knob
-        let Continuator { ix, edge,
-            length,
-            prev_ix,
-            next_ix,
-            prev,
-            next,
-            prev_length,
-            next_length } = self.boundary.windows(2).enumerate()
-            .filter_map(|(ix, edge)| if edge[0].2 == Conv::Convex && edge[1].2 == Conv::Convex {
-                // get the prev and next nodes
-                let (prev_ix, prev) = if ix == 0 {
-                    (self.boundary.len() - 1, self.boundary[self.boundary.len() - 1])
-                } else {
-                    (ix - 1, self.boundary[ix - 1])
-                };
-                let (next_ix, next) = if ix == self.boundary.len() - 2 {
-                    (0, self.boundary[0])
-                } else {
-                    (ix + 2, self.boundary[ix + 2])
-                };
-                // get previous and next edge lengths
-                let prev_length = (edge[0].0 - prev.0).abs() + (edge[0].1 - prev.1).abs();
-                let next_length = (edge[1].0 - next.0).abs() + (edge[1].1 - next.1).abs();
-                let length = (edge[0].0 - edge[1].0).abs() + (edge[0].1 - edge[1].1).abs() + 1;
-                if next.2 == Conv::Concave {
-                    if prev.2 == Conv::Convex && next_length >= prev_length {
-                        None
-                    } else {
-                        Some(Continuator{
-                            ix,
-                            edge: [edge[0], edge[1]],
-                            length,
-                            prev_ix,
-                            next_ix,
-                            prev,
-                            next,
-                            prev_length,
-                            next_length,
-                        })
-                    }
-                } else if prev.2 == Conv::Concave {
-                    if next.2 == Conv::Convex && prev_length >= next_length {
-                        None
-                    } else {
-                        Some(Continuator{
-                            ix,
-                            edge: [edge[0], edge[1]],
-                            length,
-                            prev_ix,
-                            next_ix,
-                            prev,
-                            next,
-                            prev_length,
-                            next_length,
-                        })
-                    }
-                } else if self.boundary.len() != 4 {
-                    None
-                } else {
-                    Some(Continuator{
-                        ix,
-                        edge: [edge[0], edge[1]],
-                        length,
-                        prev_ix,
-                        next_ix,
-                        prev,
-                        next,
-                        prev_length,
-                        next_length,
-                    })
-                }
-            } else {
-                None
-            })
-            .min_by_key(|c| c.length)
-            .expect("An unexpected bug in removing a continuator rectangle has been reached");
-        let is_horizontal = edge[0].0 == edge[1].0;
-        // remove the continuator rectangle
-        if prev_length == next_length {
-            for index in [prev_ix, ix, ix + 1, next_ix]
-                .into_iter()
-                .sorted()
-                .rev()
-            {
-                self.boundary.remove(index);
-            }
-        } else if prev_length < next_length {
-            if is_horizontal {
-                self.boundary[ix + 1].0 = prev.0;
-            } else {
-                self.boundary[ix + 1].1 = prev.1;
-            }
-            if ix > prev_ix {
-                self.boundary.remove(ix);
-                self.boundary.remove(prev_ix);
-            } else {
-                self.boundary.remove(prev_ix);
-                self.boundary.remove(ix);
-            }
-        } else {
-            if is_horizontal {
-                self.boundary[ix].0 = next.0;
-            } else {
-                self.boundary[ix].1 = next.1;
-            }
-            if ix + 1 > next_ix {
-                self.boundary.remove(ix + 1);
-                self.boundary.remove(next_ix);
-            } else {
-                self.boundary.remove(next_ix);
-                self.boundary.remove(ix + 1);
-            }
-        }
-        if self.boundary.is_empty() {
-            return ((prev_length + 1) * length) as u64;
-        } else {
-           return (min(prev_length, next_length) * length) as u64;
-        }
+    fn edges(&self) -> impl Iterator<Item = ((i64, i64), (i64, i64))> + '_ {
+        self.vertices
+            .iter()
+            .copied()
+            .zip(self.vertices.iter().copied().cycle().skip(1))
+    }
+
+    /// Twice the enclosed area by the shoelace formula
+    fn double_area(&self) -> i64 {
+        self.edges()
+            .map(|((r1, c1), (r2, c2))| c1 * r2 - c2 * r1)
+            .sum::<i64>()
+            .abs()
+    }
+
+    /// The number of cubes dug out for the trench itself
+    fn boundary(&self) -> i64 {
+        self.edges()
+            .map(|((r1, c1), (r2, c2))| (r1 - r2).abs() + (c1 - c2).abs())
+            .sum()
+    }
+
+    /// The cubes inside the trench and the trench itself. By Pick's theorem
+    /// the area is `interior + boundary / 2 - 1`.
+    fn lagoon(&self) -> i64 {
+        let interior = (self.double_area() - self.boundary()) / 2 + 1;
+        interior + self.boundary()
     }
 }
 
+/// Draws every segment of the trench in its own colour
+fn svg(moves: &[Move]) -> String {
+    let polygon = Polygon::from_moves(moves);
+    let rows = polygon.vertices.iter().map(|(row, _)| *row);
+    let cols = polygon.vertices.iter().map(|(_, col)| *col);
+    let (top, bottom) = (rows.clone().min().unwrap(), rows.max().unwrap());
+    let (left, right) = (cols.clone().min().unwrap(), cols.max().unwrap());
+    // a cube is one unit wide, but keep the lines visible on huge plans
+    let width = ((right - left).max(bottom - top) / 500).max(1);
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        left - width,
+        top - width,
+        right - left + 2 * width,
+        bottom - top + 2 * width
+    );
+    for (((r1, c1), (r2, c2)), m) in polygon.edges().zip(moves) {
+        out += &format!(
+            "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"square\"/>\n",
+            c1, r1, c2, r2, m.colour, width
+        );
+    }
+    out + "</svg>\n"
+}
 
-fn parse(filename: &str) -> Vec<Move> {
+fn parse(filename: &str, encoding: Encoding) -> Vec<Move> {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
@@ -236,51 +130,95 @@ fn parse(filename: &str) -> Vec<Move> {
         if length == 0 {
             break;
         }
-        let mut chars = line.trim().split(" ");
-        let dir = match chars.next().unwrap() {
-            "R" => Dir::R,
-            "D" => Dir::D,
-            "L" => Dir::L,
-            "U" => Dir::U,
-            _ => unreachable!()
+        let mut chars = line.split_whitespace();
+        let (Some(dir), Some(amount), Some(colour)) = (chars.next(), chars.next(), chars.next())
+        else {
+            line.clear();
+            continue;
+        };
+        let colour = colour.trim_matches(|c| c == '(' || c == ')').to_string();
+        let (dir, amount) = match encoding {
+            Encoding::Plain => {
+                let dir = match dir {
+                    "R" => Dir::R,
+                    "D" => Dir::D,
+                    "L" => Dir::L,
+                    "U" => Dir::U,
+                    _ => unreachable!(),
+                };
+                (dir, i64::from_str(amount).unwrap())
+            }
+            Encoding::Hex => parse_hex(&colour),
         };
-        let amount = i64::from_str(chars.next().unwrap()).unwrap();
-        let hex = chars.next().unwrap().replace('(', "").replace(')', "");
-        moves.push(Move{
+        moves.push(Move {
             dir,
             amount,
-            hex
+            colour,
         });
         line.clear();
     }
     moves
 }
 
-fn part_one(filename: &str) {
-    let moves = parse(filename);
-    let mut tunnel = Tunnel::find_boundary(&moves);
-    let mut volume = 0;
+fn lagoon(filename: &str, encoding: Encoding) -> i64 {
+    Polygon::from_moves(&parse(filename, encoding)).lagoon()
+}
 
-    while !tunnel.boundary.is_empty() {
-        volume += tunnel.next_continuator();
-    }
-    println!("Part one: {}", volume);
+fn part_one(filename: &str) {
+    println!("Part one: {}", lagoon(filename, Encoding::Plain));
 }
 
 fn part_two(filename: &str) {
-    let moves = parse(filename)
-        .into_iter()
-        .map(|m| parse_hex(&m.hex))
-        .collect::<Vec<_>>();
-    let mut tunnel = Tunnel::find_boundary(&moves);
-    let mut volume = 0;
-    while !tunnel.boundary.is_empty() {
-        volume += tunnel.next_continuator();
-    }
-    println!("Part two: {}", volume);
+    println!("Part two: {}", lagoon(filename, Encoding::Hex));
 }
 
 fn main() {
     part_one("input.txt");
     part_two("input.txt");
+    let mut args = std::env::args().skip_while(|arg| arg != "--svg").skip(1);
+    if let Some(out) = args.next() {
+        let encoding = match args.next().as_deref() {
+            Some("hex") => Encoding::Hex,
+            _ => Encoding::Plain,
+        };
+        std::fs::write(out, svg(&parse("input.txt", encoding))).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lagoon() {
+        assert_eq!(lagoon("test.txt", Encoding::Plain), 62);
+        assert_eq!(lagoon("test.txt", Encoding::Hex), 952408144115);
+        assert_eq!(parse_hex("#70c710"), (Dir::R, 461937));
+
+        let moves = parse("test.txt", Encoding::Hex);
+        assert_eq!(moves[0].colour, "#70c710");
+        let polygon = Polygon::from_moves(&moves);
+        assert_eq!(polygon.vertices.len(), moves.len());
+    }
+
+    #[test]
+    fn test_square() {
+        let moves: Vec<_> = [Dir::R, Dir::D, Dir::L, Dir::U]
+            .into_iter()
+            .map(|dir| Move {
+                dir,
+                amount: 2,
+                colour: "#000000".to_string(),
+            })
+            .collect();
+        let polygon = Polygon::from_moves(&moves);
+        assert_eq!(polygon.double_area(), 8);
+        assert_eq!(polygon.boundary(), 8);
+        assert_eq!(polygon.lagoon(), 9);
+        let drawing = svg(&moves);
+        assert!(
+            drawing.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -1 4 4\">")
+        );
+        assert_eq!(drawing.matches("stroke=\"#000000\"").count(), 4);
+    }
 }
@@ -1,54 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
-fn intersect_interval(first: [u64; 2], second: [u64; 2]) -> Option<[u64; 2]> {
-    if first[0] <= second[0] && second[0] <= first[1] && first[1] <= second[1] {
-        Some([second[0], first[1]])
-    } else if second[0] <= first[0] && first[0] <= second[1] && second[1] <= first[1] {
-        Some([first[0], second[1]])
-    } else if first[0] <= second[0] && second[1] <= first[1] {
-        Some(second)
-    } else if second[0] <= first[0] && first[1] <= second[1] {
-        Some(first)
-    } else {
-        None
-    }
-}
-
-fn intersect(first: &[[u64; 2]], second: &[[u64; 2]]) -> Vec<[u64; 2]> {
-    let mut res = vec![];
-    for f in first {
-        for s in second {
-            if let Some(int) = intersect_interval(*f, *s) {
-                res.push(int)
-            }
-        }
-    }
-    res
-}
+mod tree;
 
-fn complement(set: &[[u64; 2]]) -> Vec<[u64; 2]> {
-    let mut set = set.to_vec();
-    set.sort_by_key(|int| int[0]);
-    let mut last = 0;
-    let mut complement = vec![];
-    for s in set {
-        if s[0] == 1 {
-            last = s[1];
-            continue
-        }
-        if last + 1 <= s[0] - 1 {
-            complement.push([last + 1, s[0] - 1]);
-        }
-        last = s[1]
-    }
-    if last < 4000 {
-        complement.push([last + 1, 4000])
-    }
-    complement
-}
+use tree::Tree;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum Target {
@@ -57,121 +14,91 @@ enum Target {
     Workflow(String),
 }
 
-impl Target {
-    fn to_string(&self) -> String {
-        match &self {
-            Target::Accept => "A".to_string(),
-            Target::Reject => "R".to_string(),
-            Target::Workflow(name) => name.clone(),
-        }
-    }
-}
-
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Op {
-    Lt, Gt
+    Lt,
+    Gt,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Ranges {
-    x: Vec<[u64; 2]>,
-    m: Vec<[u64; 2]>,
-    a: Vec<[u64; 2]>,
-    s: Vec<[u64; 2]>,
+struct Check {
+    var: char,
+    op: Op,
+    value: u64,
+    target: Target,
 }
 
-impl Default for Ranges {
-    fn default() -> Self {
-        Self {
-            x: vec![[1, 4000]],
-            m: vec![[1, 4000]],
-            a: vec![[1, 4000]],
-            s: vec![[1, 4000]],
+impl Check {
+    fn matches(&self, part: &Part) -> bool {
+        match self.op {
+            Op::Lt => part.get(self.var) < self.value,
+            Op::Gt => part.get(self.var) > self.value,
         }
     }
 }
 
-impl Ranges {
-    fn intersect(&self, other: &Self) -> Self {
-        Self {
-            x: intersect(&self.x, &other.x),
-            m: intersect(&self.m, &other.m),
-            a: intersect(&self.a, &other.a),
-            s: intersect(&self.s, &other.s),
+/// The ratings of a machine part
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Part {
+    x: u64,
+    m: u64,
+    a: u64,
+    s: u64,
+}
+
+impl Part {
+    fn get(&self, var: char) -> u64 {
+        match var {
+            'x' => self.x,
+            'm' => self.m,
+            'a' => self.a,
+            's' => self.s,
+            _ => unreachable!(),
         }
     }
 
-    fn count(&self) -> u64 {
-        self.x.iter().map(|[f, s]| *s - *f + 1).sum::<u64>()
-        * self.m.iter().map(|[f, s]| *s - *f + 1).sum::<u64>()
-        * self.a.iter().map(|[f, s]| *s - *f + 1).sum::<u64>()
-        * self.s.iter().map(|[f, s]| *s - *f + 1).sum::<u64>()
+    fn rating(&self) -> u64 {
+        self.x + self.m + self.a + self.s
     }
+}
 
-    fn complement(&self, attr: char) -> Self {
-        match attr {
-            'x' =>  Self {
-                x: complement(&self.x),
-                ..self.clone()
-            },
-            'm' => Self {
-                m: complement(&self.m),
-                ..self.clone()
-            },
-            'a' => Self {
-                a: complement(&self.a),
-                ..self.clone()
-            },
-            's' => Self {
-                s: complement(&self.s),
-                ..self.clone()
-            },
-            _ => unreachable!()
+/// Parses `{x=787,m=2655,a=1222,s=2876}`
+impl FromStr for Part {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner = s
+            .trim()
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .ok_or(format!("Expected braces around {}", s))?;
+        let mut part = Part {
+            x: 0,
+            m: 0,
+            a: 0,
+            s: 0,
+        };
+        for rating in inner.split(',') {
+            let (var, value) = rating
+                .split_once('=')
+                .ok_or(format!("Expected a rating: {}", rating))?;
+            let value = u64::from_str(value).map_err(|e| format!("{}: {}", rating, e))?;
+            match var {
+                "x" => part.x = value,
+                "m" => part.m = value,
+                "a" => part.a = value,
+                "s" => part.s = value,
+                _ => return Err(format!("Unknown category: {}", var)),
+            }
         }
+        Ok(part)
     }
 }
 
-
-#[derive(Debug, Clone)]
-struct Check {
-    var: char,
-    op: Op,
-    value: u64,
-    target: Target
-}
-
 #[derive(Debug, Clone)]
 enum Rule {
     Default(Target),
-    Check(Check)
-}
-
-impl<'a> From<&'a Rule> for Ranges {
-    fn from(rule: &Rule) -> Self {
-        match rule {
-            Rule::Default(_) => Default::default(),
-            Rule::Check(check) => {
-                let mut ranges = Ranges::default();
-                let range = if check.op == Op::Gt {
-                    vec![[check.value + 1, 4000]]
-                } else {
-                    if check.value == 1 {
-                        vec![]
-                    } else {
-                        vec![[1, check.value - 1]]
-                    }
-                };
-                match check.var {
-                    'x' => ranges.x = range,
-                    'm' => ranges.m = range,
-                    'a' => ranges.a = range,
-                    's' => ranges.s = range,
-                    _ => unreachable!(),
-                };
-                ranges
-            }
-        }
-    }
+    Check(Check),
 }
 
 impl Rule {
@@ -230,31 +157,7 @@ impl Rule {
 
 #[derive(Debug, Clone)]
 struct Workflow {
-    rules: Vec<Rule>
-}
-
-impl Workflow  {
-    fn neighbors(&self) -> Vec<(&Target, Ranges)> {
-        // the complement of all accepted ranges so far
-        // i.e. the intersection of all complements
-        let mut last: Option<Ranges> = None;
-        let mut neighbors = vec![];
-        for rule in &self.rules {
-            let mut ranges: Ranges = rule.into();
-            if let Some(l) = &last {
-                ranges = l.intersect(&ranges);
-            }
-            if let Rule::Check(check) = rule {
-                last = if let Some(last) = last {
-                    Some(last.intersect(&ranges.complement(check.var)))
-                } else {
-                    Some(ranges.complement(check.var))
-                };
-            }
-            neighbors.push((rule.target(), ranges));
-        }
-        neighbors
-    }
+    rules: Vec<Rule>,
 }
 
 fn parse_workflows(filename: &str) -> HashMap<String, Workflow> {
@@ -264,7 +167,8 @@ fn parse_workflows(filename: &str) -> HashMap<String, Workflow> {
 
     let mut workflows = HashMap::new();
     while let Ok(length) = reader.read_line(&mut line) {
-        if length == 0 {
+        // the part ratings start after a blank line
+        if length == 0 || line.trim().is_empty() {
             break;
         }
         let mut split = line.trim().split('{');
@@ -272,128 +176,191 @@ fn parse_workflows(filename: &str) -> HashMap<String, Workflow> {
         let mut rest = split.next().unwrap().to_string();
         rest.pop();
         let rules = rest.split(',').map(Rule::parse).collect();
-        workflows.insert(name.clone(), Workflow{ rules });
+        workflows.insert(name.clone(), Workflow { rules });
         line.clear()
     }
     workflows
 }
 
+/// Reads the part ratings, skipping any workflows in front of them
+fn parse_parts(filename: &str) -> Vec<Part> {
+    let file = File::open(filename).unwrap();
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
 
-fn part_two(filename: &str) {
-    let workflows = parse_workflows(filename);
-    let mut stack = vec![(&workflows[&"in".to_string()], Ranges::default())] ;
-    let mut total = 0;
-    while let Some((next, possibiliites)) = stack.pop() {
-        for (n, ranges) in next.neighbors() {
-            if *n == Target::Reject {
-                continue
-            }
-            let new_ranges = ranges.intersect(&possibiliites);
-            if *n != Target::Accept {
-                stack.push((workflows.get(&n.to_string()).unwrap(), new_ranges));
-            } else {
-                total += new_ranges.count()
-            }
+    let mut parts = vec![];
+    while let Ok(length) = reader.read_line(&mut line) {
+        if length == 0 {
+            break;
+        }
+        if line.trim().starts_with('{') {
+            parts.push(Part::from_str(&line).unwrap());
         }
+        line.clear()
     }
+    parts
+}
 
+fn part_one(workflow_file: &str, parts_file: &str) {
+    let workflows = parse_workflows(workflow_file);
+    let tree = Tree::compile(&workflows).unwrap();
+    let parts = parse_parts(parts_file);
+    let total: u64 = parts
+        .iter()
+        .filter(|part| tree.accepts(part))
+        .map(Part::rating)
+        .sum();
+    println!("Part one: {}", total);
+}
+
+fn part_two(filename: &str) {
+    let workflows = parse_workflows(filename);
+    let tree = Tree::compile(&workflows).unwrap();
+    let blocks = tree.accepted();
+    let total: u64 = blocks.iter().map(tree::volume).sum();
     println!("Part two: {}", total);
+    if std::env::args().any(|arg| arg == "--blocks") {
+        for block in &blocks {
+            println!("{}", tree::describe(block));
+        }
+    }
 }
+
 fn main() {
+    part_one("input.txt", "parts.txt");
     part_two("input.txt");
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_intersection() {
-        let first = vec![[1u64, 100], [200, 300]];
-        let second = vec![[50u64, 250], [251, 252], [270, 400]];
-        let expected = vec![[50u64, 100], [200, 250], [251, 252], [270, 300]];
-        let mut res = intersect(&first, &second);
-        res.sort_by_key(|int| int[0]);
-        assert_eq!(expected, res);
+    fn workflows(lines: &[&str]) -> HashMap<String, Workflow> {
+        lines
+            .iter()
+            .map(|line| {
+                let (name, rules) = line.trim_end_matches('}').split_once('{').unwrap();
+                let rules = rules.split(',').map(Rule::parse).collect();
+                (name.to_string(), Workflow { rules })
+            })
+            .collect()
     }
 
     #[test]
-    fn test_complement() {
-        let set = vec![[1u64, 100], [200, 250], [251, 252], [254, 300]];
-        let expected = vec![[101u64, 199], [253, 253], [301, 4000]];
-        assert_eq!(complement(&set), expected);
-        assert_eq!(complement(&vec![]), vec![[1, 4000]]);
-        assert!(complement(&vec![[1, 4000]]).is_empty());
+    fn test_rule_blocks() {
+        let accepted = |lines: &[&str]| Tree::compile(&workflows(lines)).unwrap().accepted();
+        assert_eq!(accepted(&["in{A}"]), vec![[[1, 4000]; 4]]);
+        assert!(accepted(&["in{R}"]).is_empty());
+        assert_eq!(
+            accepted(&["in{x>10:A,R}"]),
+            vec![[[11, 4000], [1, 4000], [1, 4000], [1, 4000]]]
+        );
+        assert_eq!(
+            accepted(&["in{x>10:R,A}"]),
+            vec![[[1, 10], [1, 4000], [1, 4000], [1, 4000]]]
+        );
+        assert!(accepted(&["in{x<1:A,R}"]).is_empty());
     }
 
     #[test]
-    fn test_rule_to_ranges() {
-        let rule = Rule::Default(Target::Reject);
-        let ranges: Ranges = (&rule).into();
-        assert_eq!(ranges, Ranges {
-            x: vec![[1, 4000]],
-            m: vec![[1, 4000]],
-            a: vec![[1, 4000]],
-            s: vec![[1, 4000]],
-        });
-
-        let rule = Rule::Check(Check{
-            var: 'x',
-            op: Op::Gt,
-            value: 10,
-            target: Target::Workflow("one".to_string()),
-        });
-        let ranges: Ranges = (&rule).into();
-        assert_eq!(ranges, Ranges {
-            x: vec![[11, 4000]],
-            m: vec![[1, 4000]],
-            a: vec![[1, 4000]],
-            s: vec![[1, 4000]],
-        });
-        assert_eq!(ranges.complement('x'), Ranges {
-            x: vec![[1, 10]],
-            m: vec![[1, 4000]],
-            a: vec![[1, 4000]],
-            s: vec![[1, 4000]],
-        });
+    fn test_workflow_blocks() {
+        let tree = Tree::compile(&workflows(&[
+            "in{x>10:one,m<20:two,a>30:R,A}",
+            "one{A}",
+            "two{A}",
+        ]))
+        .unwrap();
+        let blocks = tree.accepted();
+        assert_eq!(
+            blocks,
+            vec![
+                [[11, 4000], [1, 4000], [1, 4000], [1, 4000]],
+                [[1, 10], [1, 19], [1, 4000], [1, 4000]],
+                [[1, 10], [20, 4000], [1, 30], [1, 4000]],
+            ]
+        );
+        assert_eq!(tree::volume(&blocks[2]), 10 * 3981 * 30 * 4000);
     }
 
     #[test]
-    fn test_workflow_neighbors() {
-        let wf = Workflow {
-            rules: vec![
-                Rule::parse("x>10:one"),
-                Rule::parse("m<20:two"),
-                Rule::parse("a>30:R"),
-                Rule::Default(Target::Accept)],
-        };
-
-        let expected = vec![
-            (
-                Target::Workflow("one".to_string()),
-                Ranges{x: vec![[11, 4000]], m: vec![[1, 4000]], a: vec![[1, 4000]], s: vec![[1, 4000]]}
-            ),
-            (
-                Target::Workflow("two".to_string()),
-                Ranges{x: vec![[1, 10]], m: vec![[1, 19]], a: vec![[1, 4000]],s: vec![[1, 4000]]}
-            ),
-            (
-                Target::Reject,
-                Ranges{x: vec![[1, 10]], m: vec![[20, 4000]], a: vec![[31, 4000]], s: vec![[1, 4000]]}
-            ),
-            (
-                Target::Accept,
-                Ranges{x: vec![[1, 10]], m: vec![[20, 4000]], a: vec![[1, 30]], s: vec![[1, 4000]]}
-            ),
-        ];
+    fn test_parts() {
+        let workflows = parse_workflows("test.txt");
+        let parts = parse_parts("test_parts.txt");
+        assert_eq!(
+            parts[0],
+            Part {
+                x: 787,
+                m: 2655,
+                a: 1222,
+                s: 2876
+            }
+        );
+        let tree = Tree::compile(&workflows).unwrap();
+        let accepted: Vec<_> = parts.iter().filter(|p| tree.accepts(p)).collect();
+        assert_eq!(accepted.len(), 3);
+        assert_eq!(accepted.iter().map(|p| p.rating()).sum::<u64>(), 19114);
+
+        let blocks = tree.accepted();
+        assert_eq!(
+            blocks.iter().map(tree::volume).sum::<u64>(),
+            167409079868000
+        );
+        // every part lies in a block exactly when it is accepted
+        for part in &parts {
+            let inside = blocks
+                .iter()
+                .filter(|block| {
+                    "xmas"
+                        .chars()
+                        .zip(block.iter())
+                        .all(|(var, [lo, hi])| (*lo..=*hi).contains(&part.get(var)))
+                })
+                .count();
+            assert_eq!(inside, tree.accepts(part) as usize);
+        }
+    }
 
-        let res : Vec<(Target, Ranges)> = wf.neighbors()
-            .into_iter()
-            .map(|(k, v)| (k.clone(), v))
-            .collect();
-        assert_eq!(expected, res);
-        let res = Ranges{x: vec![[1, 10]], m: vec![[20, 4000]], a: vec![[1, 30]], s: vec![[1, 4000]]}.count();
-        assert_eq!(10 * 3981 * 30 * 4000, res);
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            tree::validate(&workflows(&["px{a<2006:A,R}"])),
+            Err("There is no workflow named in".to_string())
+        );
+        assert_eq!(
+            tree::validate(&workflows(&["in{a<2006:px,R}"])),
+            Err("in sends parts to a missing workflow px".to_string())
+        );
+        assert_eq!(
+            Tree::compile(&workflows(&["in{a<2006:px,R}", "px{m>10:qs,A}", "qs{in}"])),
+            Err("The workflows loop: in -> px -> qs -> in".to_string())
+        );
+        assert_eq!(
+            Tree::compile(&workflows(&["in{a<2006:px,R}", "px{m>10:A,A}"])),
+            Ok(Tree::Split {
+                check: Check {
+                    var: 'a',
+                    op: Op::Lt,
+                    value: 2006,
+                    target: Target::Workflow("px".to_string())
+                },
+                pass: Box::new(Tree::Leaf(true)),
+                fail: Box::new(Tree::Leaf(false)),
+            })
+        );
+        assert_eq!(
+            Tree::compile(&workflows(&["in{a<2006:px}", "px{A}"])),
+            Err("in does not end in a default rule".to_string())
+        );
+        let empty = HashMap::from([("in".to_string(), Workflow { rules: vec![] })]);
+        assert_eq!(
+            Tree::compile(&empty),
+            Err("in does not end in a default rule".to_string())
+        );
+        // no rating is below zero, so that branch holds no block
+        let tree = Tree::compile(&workflows(&["in{x<0:A,m<11:A,R}"])).unwrap();
+        assert_eq!(
+            tree.accepted(),
+            vec![[[1, 4000], [1, 10], [1, 4000], [1, 4000]]]
+        );
     }
-}
\ No newline at end of file
+}
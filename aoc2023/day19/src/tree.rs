@@ -0,0 +1,181 @@
+use super::*;
+
+/// The inclusive range of each of the ratings x, m, a and s
+pub type Block = [[u64; 2]; 4];
+
+const FULL: Block = [[1, 4000]; 4];
+
+fn index(var: char) -> usize {
+    match var {
+        'x' => 0,
+        'm' => 1,
+        'a' => 2,
+        's' => 3,
+        _ => unreachable!(),
+    }
+}
+
+/// The workflows with every name resolved: each node checks a single
+/// rating and each leaf accepts or rejects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree {
+    Leaf(bool),
+    Split {
+        check: Check,
+        pass: Box<Tree>,
+        fail: Box<Tree>,
+    },
+}
+
+/// Make sure the workflows can be run: `in` exists, every workflow ends
+/// in a default rule, every target names a workflow and no part can be
+/// sent round in circles.
+pub fn validate(workflows: &HashMap<String, Workflow>) -> Result<(), String> {
+    if !workflows.contains_key("in") {
+        return Err("There is no workflow named in".to_string());
+    }
+    for (name, workflow) in workflows {
+        if !matches!(workflow.rules.last(), Some(Rule::Default(_))) {
+            return Err(format!("{} does not end in a default rule", name));
+        }
+        for rule in &workflow.rules {
+            if let Target::Workflow(target) = rule.target() {
+                if !workflows.contains_key(target) {
+                    return Err(format!(
+                        "{} sends parts to a missing workflow {}",
+                        name, target
+                    ));
+                }
+            }
+        }
+    }
+
+    // depth first, keeping the workflows on the current path in order
+    fn visit<'a>(
+        workflows: &'a HashMap<String, Workflow>,
+        name: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Result<(), String> {
+        if let Some(start) = path.iter().position(|n| *n == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            return Err(format!("The workflows loop: {}", cycle.join(" -> ")));
+        }
+        if done.contains(name) {
+            return Ok(());
+        }
+        path.push(name);
+        for rule in &workflows[name].rules {
+            if let Target::Workflow(target) = rule.target() {
+                visit(workflows, target, path, done)?;
+            }
+        }
+        path.pop();
+        done.insert(name);
+        Ok(())
+    }
+
+    let mut done = HashSet::new();
+    let mut names: Vec<_> = workflows.keys().collect();
+    names.sort();
+    for name in names {
+        visit(workflows, name, &mut vec![], &mut done)?;
+    }
+    Ok(())
+}
+
+impl Tree {
+    /// Inline the workflows reachable from `in`. A workflow used from
+    /// several places is copied into each of them.
+    pub fn compile(workflows: &HashMap<String, Workflow>) -> Result<Self, String> {
+        validate(workflows)?;
+        Ok(Self::rules(workflows, &workflows["in"].rules))
+    }
+
+    fn rules(workflows: &HashMap<String, Workflow>, rules: &[Rule]) -> Self {
+        match &rules[0] {
+            Rule::Default(target) => Self::target(workflows, target),
+            Rule::Check(check) => {
+                let pass = Self::target(workflows, &check.target);
+                let fail = Self::rules(workflows, &rules[1..]);
+                match (pass, fail) {
+                    (Tree::Leaf(pass), Tree::Leaf(fail)) if pass == fail => Tree::Leaf(pass),
+                    (pass, fail) => Tree::Split {
+                        check: check.clone(),
+                        pass: Box::new(pass),
+                        fail: Box::new(fail),
+                    },
+                }
+            }
+        }
+    }
+
+    fn target(workflows: &HashMap<String, Workflow>, target: &Target) -> Self {
+        match target {
+            Target::Accept => Tree::Leaf(true),
+            Target::Reject => Tree::Leaf(false),
+            Target::Workflow(name) => Self::rules(workflows, &workflows[name].rules),
+        }
+    }
+
+    pub fn accepts(&self, part: &Part) -> bool {
+        match self {
+            Tree::Leaf(accept) => *accept,
+            Tree::Split { check, pass, fail } => {
+                if check.matches(part) {
+                    pass.accepts(part)
+                } else {
+                    fail.accepts(part)
+                }
+            }
+        }
+    }
+
+    /// The disjoint blocks of ratings that are accepted
+    pub fn accepted(&self) -> Vec<Block> {
+        let mut blocks = vec![];
+        self.collect(FULL, &mut blocks);
+        blocks
+    }
+
+    fn collect(&self, block: Block, blocks: &mut Vec<Block>) {
+        if block.iter().any(|[lo, hi]| lo > hi) {
+            return;
+        }
+        match self {
+            Tree::Leaf(true) => blocks.push(block),
+            Tree::Leaf(false) => {}
+            Tree::Split { check, pass, fail } => {
+                let ix = index(check.var);
+                let [lo, hi] = block[ix];
+                let (passing, failing) = match check.op {
+                    Op::Lt => (
+                        [lo, hi.min(check.value.saturating_sub(1))],
+                        [lo.max(check.value), hi],
+                    ),
+                    Op::Gt => ([lo.max(check.value + 1), hi], [lo, hi.min(check.value)]),
+                };
+                let mut next = block;
+                next[ix] = passing;
+                pass.collect(next, blocks);
+                next[ix] = failing;
+                fail.collect(next, blocks);
+            }
+        }
+    }
+}
+
+/// The number of combinations of ratings in the block
+pub fn volume(block: &Block) -> u64 {
+    block.iter().map(|[lo, hi]| hi - lo + 1).product()
+}
+
+pub fn describe(block: &Block) -> String {
+    "xmas"
+        .chars()
+        .zip(block)
+        .map(|(var, [lo, hi])| format!("{} in {}..={}", var, lo, hi))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
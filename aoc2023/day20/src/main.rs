@@ -1,10 +1,13 @@
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::{Debug, Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+mod trace;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum Module {
+    Broadcaster(Vec<String>),
     FlipFlop(FlipFlop),
     Conj(Conjunction),
 }
@@ -12,10 +15,19 @@ enum Module {
 impl Module {
     fn receive(&mut self, src: String, pulse: bool) -> Option<(&[String], bool)> {
         match self {
+            Self::Broadcaster(targets) => Some((targets, pulse)),
             Self::FlipFlop(flip) => flip.receive(pulse),
             Self::Conj(conj) => conj.receive(src, pulse),
         }
     }
+
+    fn targets(&self) -> &[String] {
+        match self {
+            Self::Broadcaster(targets) => targets,
+            Self::FlipFlop(flip) => &flip.targets,
+            Self::Conj(conj) => &conj.targets,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -61,68 +73,72 @@ struct Pulse {
 
 impl Display for Pulse {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let str = format!("{} sends {} -> {}", self.src, if self.pulse {"high"} else {"low"}, self.target);
+        let str = format!(
+            "{} sends {} -> {}",
+            self.src,
+            if self.pulse { "high" } else { "low" },
+            self.target
+        );
         f.write_str(&str)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Circuit {
-    broadcaster: Vec<String>,
     pulse_queue: VecDeque<Pulse>,
-    modules: BTreeMap<String, Module>
+    modules: BTreeMap<String, Module>,
 }
 
-enum ButtonResult {
-    Pulse(bool),
-    Halt,
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
 
-impl Circuit {
+fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd(a, b) * b
+}
 
-    fn initialize(&mut self) {
-        for target in &self.broadcaster {
-            self.pulse_queue.push_back(Pulse {
-                pulse: false,
-                src: "broadcaster".to_string(),
-                target: target.to_string(),
-            });
-        }
-    }
+/// Give up looking for the feeders' periods after this many presses
+const MAX_PRESSES: u64 = 100_000;
 
-    fn push_button(&mut self, stop_on: &str) -> Option<(u64, u64)> {
-        let mut lows = 1;
+impl Circuit {
+    /// Send a low pulse to the broadcaster and run until the circuit
+    /// settles, showing every pulse to `watch`. Returns the number of low
+    /// and high pulses sent.
+    fn push_button(&mut self, mut watch: impl FnMut(&Pulse)) -> (u64, u64) {
+        self.press();
+        let mut lows = 0;
         let mut highs = 0;
-        loop {
-            if self.pulse_queue.is_empty() {
-                break;
-            }
-            match self.pulse(stop_on) {
-                Some(ButtonResult::Halt) => return None,
-                Some(ButtonResult::Pulse(pulse)) => if pulse {
-                    highs += 1;
-                } else {
-                    lows += 1;
-                }
-                None => {}
+        while let Some(pulse) = self.pulse() {
+            if pulse.pulse {
+                highs += 1;
+            } else {
+                lows += 1;
             }
+            watch(&pulse);
         }
-        self.initialize();
-        Some((lows, highs))
+        (lows, highs)
     }
 
-    fn pulse(&mut self, stop_on: &str) -> Option<ButtonResult> {
-        let Some(pulse) = self.pulse_queue.pop_front() else {
-            return None
-        };
-        if pulse.target.as_str() == stop_on && !pulse.pulse {
-            return Some(ButtonResult::Halt)
-        }
+    /// Queue the low pulse from the button to the broadcaster
+    fn press(&mut self) {
+        self.pulse_queue.push_back(Pulse {
+            pulse: false,
+            src: "button".to_string(),
+            target: "broadcaster".to_string(),
+        });
+    }
+
+    /// Deliver the next pulse in the queue and return it
+    fn pulse(&mut self) -> Option<Pulse> {
+        let pulse = self.pulse_queue.pop_front()?;
         if let Some(module) = self.modules.get_mut(&pulse.target) {
-            if let Some((targets, new_pulse)) = module
-                .receive(pulse.src, pulse.pulse) {
+            if let Some((targets, new_pulse)) = module.receive(pulse.src.clone(), pulse.pulse) {
                 for target in targets {
-                    self.pulse_queue.push_back(Pulse{
+                    self.pulse_queue.push_back(Pulse {
                         pulse: new_pulse,
                         src: pulse.target.to_string(),
                         target: target.to_string(),
@@ -130,113 +146,302 @@ impl Circuit {
                 }
             }
         }
-        Some(ButtonResult::Pulse(pulse.pulse))
+        Some(pulse)
+    }
+
+    /// The modules that send pulses to `name`
+    fn inputs(&self, name: &str) -> Vec<&str> {
+        self.modules
+            .iter()
+            .filter(|(_, m)| m.targets().iter().any(|t| t == name))
+            .map(|(n, _)| n.as_str())
+            .collect()
+    }
+
+    /// `rx` gets a low pulse when the single conjunction driving it has
+    /// last heard high from all of its inputs. Each input is the output
+    /// of a counter which sends high once every period presses, so find
+    /// the periods and combine them.
+    fn presses_until_low(&self, name: &str) -> Result<u64, String> {
+        let driver = match self.inputs(name).as_slice() {
+            [driver] => *driver,
+            drivers => {
+                return Err(format!(
+                    "Expected one module driving {}, found {:?}",
+                    name, drivers
+                ))
+            }
+        };
+        if !matches!(self.modules[driver], Module::Conj(_)) {
+            return Err(format!(
+                "{} is driven by {} which is not a conjunction",
+                name, driver
+            ));
+        }
+        let mut highs: BTreeMap<&str, Vec<u64>> = self
+            .inputs(driver)
+            .into_iter()
+            .map(|feeder| (feeder, vec![]))
+            .collect();
+        if highs.is_empty() {
+            return Err(format!("{} has no inputs", driver));
+        }
+
+        let mut circuit = self.clone();
+        let mut presses = 0;
+        while highs.values().any(|seen| seen.len() < 2) {
+            presses += 1;
+            if presses > MAX_PRESSES {
+                return Err(format!(
+                    "The inputs of {} did not repeat within {} presses",
+                    driver, MAX_PRESSES
+                ));
+            }
+            circuit.push_button(|pulse| {
+                if pulse.pulse && pulse.target == driver {
+                    if let Some(seen) = highs.get_mut(pulse.src.as_str()) {
+                        if seen.last() != Some(&presses) {
+                            seen.push(presses);
+                        }
+                    }
+                }
+            });
+        }
+
+        let mut total = 1;
+        for (feeder, seen) in highs {
+            let (first, period) = (seen[0], seen[1] - seen[0]);
+            if first != period {
+                return Err(format!(
+                    "{} first sends high after {} presses but repeats every {}",
+                    feeder, first, period
+                ));
+            }
+            total = lcm(total, period);
+        }
+        Ok(total)
+    }
+
+    /// The circuit in Graphviz format: flip-flops are boxes and
+    /// conjunctions are diamonds
+    fn dot(&self) -> String {
+        let mut out = "digraph circuit {\n".to_string();
+        for (name, module) in &self.modules {
+            let shape = match module {
+                Module::Broadcaster(_) => "doublecircle",
+                Module::FlipFlop(_) => "box",
+                Module::Conj(_) => "diamond",
+            };
+            out += &format!("  {} [shape={}];\n", name, shape);
+        }
+        for (name, module) in &self.modules {
+            for target in module.targets() {
+                out += &format!("  {} -> {};\n", name, target);
+            }
+        }
+        out + "}\n"
     }
 }
 
+fn parse_targets(targets: &str) -> Vec<String> {
+    targets.split(',').map(|t| t.trim().to_string()).collect()
+}
+
 fn parse(filename: &str) -> Circuit {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
 
     let mut circuit = Circuit {
-        broadcaster: vec![],
         pulse_queue: Default::default(),
         modules: Default::default(),
     };
-    let mut conjs = HashMap::<String, Conjunction>::new();
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
             break;
         }
-        if line.contains("broadcaster") {
-            let broadcaster = line.trim().split("->").nth(1).unwrap();
-            circuit.broadcaster = broadcaster.split(',')
-                .into_iter()
-                .map(|x| x.to_string())
-                .collect()
-        } else if line.contains('%') {
-            let flip_flop = line.trim().replace('%', "");
-            let mut flip_flop = flip_flop.split("->");
-            let name = flip_flop.next().unwrap();
-            let targets = flip_flop.next().unwrap();
-            let targets = targets.split(',').map(|t| t.to_string()).collect();
-            circuit.modules.insert(name.to_string(), Module::FlipFlop(FlipFlop{
-                status: false,
-                targets,
-            }));
-        } else if line.contains('&') {
-            let conj = line.trim().replace('&', "");
-            let mut conj = conj.split("->");
-            let name = conj.next().unwrap();
-            let targets = conj.next().unwrap();
-            let targets = targets.split(',').map(|t| t.to_string()).collect();
-            conjs.insert(name.to_string(),Conjunction{
-                remembered: Default::default(),
-                targets,
-            });
+        if let Some((name, targets)) = line.trim().split_once("->") {
+            let name = name.trim();
+            let targets = parse_targets(targets);
+            let (name, module) = if let Some(name) = name.strip_prefix('%') {
+                let flip = FlipFlop {
+                    status: false,
+                    targets,
+                };
+                (name, Module::FlipFlop(flip))
+            } else if let Some(name) = name.strip_prefix('&') {
+                let conj = Conjunction {
+                    remembered: Default::default(),
+                    targets,
+                };
+                (name, Module::Conj(conj))
+            } else {
+                (name, Module::Broadcaster(targets))
+            };
+            circuit.modules.insert(name.to_string(), module);
         }
         line.clear();
     }
-    for (conj, m) in conjs.iter_mut() {
-        let inputs: Vec<_> = circuit.modules
-            .iter()
-            .filter_map(|(n, m)| if let Module::FlipFlop(flip) = m {
-                flip.targets.contains(conj).then_some(n)
-            } else {
-                None
-            }).collect();
-        m.remembered = inputs
+    // conjunctions start out remembering a low pulse from every input
+    let names: Vec<String> = circuit.modules.keys().cloned().collect();
+    for name in names {
+        let inputs: Vec<String> = circuit
+            .inputs(&name)
             .into_iter()
-            .map(|n| (n.to_string(), false))
+            .map(String::from)
             .collect();
-
-    }
-    for (name, conj) in conjs {
-        circuit.modules.insert(name, Module::Conj(conj));
+        if let Some(Module::Conj(conj)) = circuit.modules.get_mut(&name) {
+            conj.remembered = inputs.into_iter().map(|n| (n, false)).collect();
+        }
     }
     circuit
 }
 
 fn part_one(filename: &str) {
     let mut circuit = parse(filename);
-    circuit.initialize();
     let mut lows = 0;
     let mut highs = 0;
     for _ in 0..1000 {
-        let (new_lows, new_highs) = circuit.push_button("").unwrap();
+        let (new_lows, new_highs) = circuit.push_button(|_| {});
         lows += new_lows;
         highs += new_highs;
     }
     println!("Part one: {}", lows * highs);
 }
 
-
-/// The `rx` module receives output when all of `ks`, `jf`, `qs` and
-/// `zk` do. We calculate the number of iterations for each of these
-///  and compute the lcm (they all turn out to be prime).
 fn part_two(filename: &str) {
-    let mut circuit = parse(filename);
-    circuit.initialize();
-    let compute_iters = |stop_on: &str, mut circuit: Circuit| {
-        let mut iters = 1u64;
-        loop {
-            if circuit.push_button(stop_on).is_none() {
-                break;
-            }
-            iters += 1;
-        }
-        iters
-    };
-
-    let res = compute_iters("ks", circuit.clone())
-        * compute_iters("jf", circuit.clone())
-        * compute_iters("qs", circuit.clone())
-        * compute_iters("zk", circuit.clone());
-    println!("Part two: {}", res);
+    let circuit = parse(filename);
+    match circuit.presses_until_low("rx") {
+        Ok(presses) => println!("Part two: {}", presses),
+        Err(e) => println!("Part two: {}", e),
+    }
 }
 
 fn main() {
     part_one("input.txt");
     part_two("input.txt");
+    let flag = |name: &str| std::env::args().skip_while(|arg| arg != name).nth(1);
+    if let Some(out) = flag("--dot") {
+        std::fs::write(out, parse("input.txt").dot()).unwrap();
+    }
+    if let Some(out) = flag("--trace") {
+        let filter = trace::Filter {
+            module: flag("--module"),
+            level: flag("--level").map(|level| level == "high"),
+        };
+        let presses = match flag("--presses").map_or(Ok(1), |n| n.parse::<u64>()) {
+            Ok(presses) => presses,
+            Err(e) => {
+                println!("Invalid number of presses: {}", e);
+                return;
+            }
+        };
+        let jsonl = std::env::args().any(|arg| arg == "--jsonl");
+        let pulses = trace::Stepper::new(parse("input.txt")).record(presses, &filter);
+        let mut file = File::create(&out).unwrap();
+        trace::dump(&pulses, &mut file, jsonl).unwrap();
+    }
+    if std::env::args().any(|arg| arg == "--step") {
+        trace::interactive(parse("input.txt"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_button() {
+        let mut circuit = parse("test.txt");
+        let mut sent = vec![];
+        circuit.push_button(|pulse| sent.push(pulse.to_string()));
+        assert_eq!(
+            sent,
+            [
+                "button sends low -> broadcaster",
+                "broadcaster sends low -> a",
+                "a sends high -> inv",
+                "a sends high -> con",
+                "inv sends low -> b",
+                "con sends high -> output",
+                "b sends high -> con",
+                "con sends low -> output",
+            ]
+        );
+        let mut circuit = parse("test.txt");
+        let (lows, highs) = (0..1000)
+            .map(|_| circuit.push_button(|_| {}))
+            .fold((0, 0), |(l, h), (nl, nh)| (l + nl, h + nh));
+        assert_eq!(lows * highs, 11687500);
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(3, 5), 15);
+    }
+
+    #[test]
+    fn test_presses_until_low() {
+        assert_eq!(
+            parse("test.txt").presses_until_low("rx"),
+            Err("Expected one module driving rx, found []".to_string())
+        );
+        // without any counters rx would never get a low pulse
+        assert_eq!(
+            parse("test_no_inputs.txt").presses_until_low("rx"),
+            Err("hj has no inputs".to_string())
+        );
+        // counters of three and five presses, which set the missing bits
+        // and carry back to zero once they are reached
+        let circuit = parse("test_counters.txt");
+        assert_eq!(circuit.inputs("hj"), ["ia", "ib"]);
+        let dot = circuit.dot();
+        assert!(dot.contains("  hj [shape=diamond];\n"));
+        assert!(dot.contains("  hj -> rx;\n"));
+
+        let mut brute = circuit.clone();
+        let mut presses = 0;
+        let mut low = false;
+        while !low {
+            presses += 1;
+            brute.push_button(|pulse| low |= pulse.target == "rx" && !pulse.pulse);
+        }
+        assert_eq!(presses, 15);
+        assert_eq!(circuit.presses_until_low("rx"), Ok(presses));
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut stepper = trace::Stepper::new(parse("test.txt"));
+        let first = stepper.finish_press();
+        assert_eq!(first.len(), 8);
+        assert_eq!(first[3].to_string(), "1.4: a sends high -> con");
+        assert_eq!(
+            first[3].to_json(),
+            r#"{"press":1,"seq":4,"src":"a","pulse":"high","target":"con"}"#
+        );
+
+        let filter = trace::Filter {
+            module: Some("output".to_string()),
+            level: Some(false),
+        };
+        let lows = stepper.record(3, &filter);
+        let mut text = vec![];
+        trace::dump(&lows, &mut text, false).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "3.6: con sends low -> output\n"
+        );
+
+        // `a` switching on makes `inv` send low to `b`
+        let mut stepper = trace::Stepper::new(parse("test.txt"));
+        let pulses = stepper.until_low("b", 10).unwrap();
+        assert_eq!(
+            pulses.last().unwrap().to_string(),
+            "1.5: inv sends low -> b"
+        );
+        assert_eq!(stepper.step().to_string(), "1.6: con sends high -> output");
+        assert!(stepper.until_low("nowhere", 10).is_none());
+    }
 }
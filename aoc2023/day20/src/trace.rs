@@ -0,0 +1,166 @@
+use super::*;
+use std::io::Write;
+
+/// A pulse along with the press it belongs to and its place in that press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Traced {
+    pub press: u64,
+    pub seq: u64,
+    pub pulse: Pulse,
+}
+
+impl Display for Traced {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}: {}", self.press, self.seq, self.pulse)
+    }
+}
+
+impl Traced {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"press\":{},\"seq\":{},\"src\":\"{}\",\"pulse\":\"{}\",\"target\":\"{}\"}}",
+            self.press,
+            self.seq,
+            self.pulse.src,
+            if self.pulse.pulse { "high" } else { "low" },
+            self.pulse.target
+        )
+    }
+}
+
+/// Which pulses to keep: those sent or received by `module` and those of
+/// the given level. `None` keeps everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub module: Option<String>,
+    pub level: Option<bool>,
+}
+
+impl Filter {
+    pub fn matches(&self, pulse: &Pulse) -> bool {
+        self.module
+            .as_ref()
+            .is_none_or(|m| pulse.src == *m || pulse.target == *m)
+            && self.level.is_none_or(|level| pulse.pulse == level)
+    }
+}
+
+/// Presses the button and numbers every pulse as it is delivered
+#[derive(Debug, Clone)]
+pub struct Stepper {
+    circuit: Circuit,
+    press: u64,
+    seq: u64,
+}
+
+impl Stepper {
+    pub fn new(circuit: Circuit) -> Self {
+        Self {
+            circuit,
+            press: 0,
+            seq: 0,
+        }
+    }
+
+    /// Deliver a single pulse, pressing the button first if the circuit
+    /// has settled
+    pub fn step(&mut self) -> Traced {
+        if self.circuit.pulse_queue.is_empty() {
+            self.circuit.press();
+            self.press += 1;
+            self.seq = 0;
+        }
+        let pulse = self.circuit.pulse().unwrap();
+        self.seq += 1;
+        Traced {
+            press: self.press,
+            seq: self.seq,
+            pulse,
+        }
+    }
+
+    /// Deliver pulses until the circuit settles
+    pub fn finish_press(&mut self) -> Vec<Traced> {
+        let mut pulses = vec![self.step()];
+        while !self.circuit.pulse_queue.is_empty() {
+            pulses.push(self.step());
+        }
+        pulses
+    }
+
+    /// Deliver pulses until `module` receives a low one, giving up after
+    /// `max_presses` more presses
+    pub fn until_low(&mut self, module: &str, max_presses: u64) -> Option<Vec<Traced>> {
+        let last = self.press + max_presses;
+        let mut pulses = vec![];
+        loop {
+            if self.circuit.pulse_queue.is_empty() && self.press == last {
+                return None;
+            }
+            let traced = self.step();
+            let done = traced.pulse.target == module && !traced.pulse.pulse;
+            pulses.push(traced);
+            if done {
+                return Some(pulses);
+            }
+        }
+    }
+
+    /// Press the button `presses` times and keep the pulses passing the filter
+    pub fn record(&mut self, presses: u64, filter: &Filter) -> Vec<Traced> {
+        (0..presses)
+            .flat_map(|_| self.finish_press())
+            .filter(|traced| filter.matches(&traced.pulse))
+            .collect()
+    }
+}
+
+/// Write one pulse per line, as JSON objects if `jsonl`
+pub fn dump(trace: &[Traced], out: &mut impl Write, jsonl: bool) -> std::io::Result<()> {
+    for traced in trace {
+        if jsonl {
+            writeln!(out, "{}", traced.to_json())?;
+        } else {
+            writeln!(out, "{}", traced)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read commands from stdin: `n` delivers the next pulse, `p` finishes the
+/// current press, `u <module>` runs until the module receives low and `q`
+/// quits.
+pub fn interactive(circuit: Circuit) {
+    let mut stepper = Stepper::new(circuit);
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().unwrap();
+        line.clear();
+        if stdin.read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        let mut words = line.split_whitespace();
+        let pulses = match (words.next(), words.next()) {
+            (Some("n"), None) | (None, None) => vec![stepper.step()],
+            (Some("p"), None) => stepper.finish_press(),
+            (Some("u"), Some(module)) => match stepper.until_low(module, MAX_PRESSES) {
+                // only the pulse that stopped the run
+                Some(mut pulses) => vec![pulses.pop().unwrap()],
+                None => {
+                    println!("{} got no low pulse in {} presses", module, MAX_PRESSES);
+                    continue;
+                }
+            },
+            (Some("q"), None) => break,
+            _ => {
+                println!("Commands: n, p, u <module>, q");
+                continue;
+            }
+        };
+        for traced in pulses {
+            println!("{}", traced);
+        }
+    }
+}
@@ -3,7 +3,6 @@ use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader, Write};
 
-
 #[derive(Debug, Clone)]
 struct Grid {
     start: (i64, i64),
@@ -16,11 +15,11 @@ impl Grid {
     fn neighbors(&self, (row, col): (i64, i64)) -> [Option<(i64, i64)>; 4] {
         let inner_row = if row < 0 {
             let maybe_row = (row % self.rows as i64) + self.rows as i64;
-             if maybe_row == self.rows as i64 {
-                 0
-             } else {
-                 maybe_row
-             }
+            if maybe_row == self.rows as i64 {
+                0
+            } else {
+                maybe_row
+            }
         } else {
             row % self.rows as i64
         } as usize;
@@ -37,38 +36,46 @@ impl Grid {
 
         let mut neighbors: [Option<(i64, i64)>; 4] = [None; 4];
         if inner_row > 0 {
-            neighbors[0] = self.plots
+            neighbors[0] = self
+                .plots
                 .contains(&(inner_row - 1, inner_col))
                 .then_some((row - 1, col));
         } else {
-            neighbors[0] = self.plots
+            neighbors[0] = self
+                .plots
                 .contains(&(self.rows - 1, inner_col))
                 .then_some((row - 1, col));
         }
         if inner_col > 0 {
-            neighbors[1] = self.plots
+            neighbors[1] = self
+                .plots
                 .contains(&(inner_row, inner_col - 1))
                 .then_some((row, col - 1));
         } else {
-            neighbors[1] = self.plots
+            neighbors[1] = self
+                .plots
                 .contains(&(inner_row, self.cols - 1))
                 .then_some((row, col - 1));
         }
         if inner_row + 1 == self.rows {
-            neighbors[2] = self.plots
+            neighbors[2] = self
+                .plots
                 .contains(&(0, inner_col))
                 .then_some((row + 1, col));
         } else {
-            neighbors[2] = self.plots
+            neighbors[2] = self
+                .plots
                 .contains(&(inner_row + 1, inner_col))
                 .then_some((row + 1, col));
         }
         if inner_col + 1 == self.cols {
-            neighbors[3] = self.plots
+            neighbors[3] = self
+                .plots
                 .contains(&(inner_row, 0))
                 .then_some((row, col + 1));
         } else {
-            neighbors[3] = self.plots
+            neighbors[3] = self
+                .plots
                 .contains(&(inner_row, inner_col + 1))
                 .then_some((row, col + 1));
         }
@@ -77,7 +84,7 @@ impl Grid {
     }
 }
 
-fn parse(filename: &str) -> Grid{
+fn parse(filename: &str) -> Grid {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
@@ -87,8 +94,6 @@ fn parse(filename: &str) -> Grid{
         rows: 0,
         cols: 0,
     };
-    let mut odd_rocks = 0;
-    let mut even_rocks = 0;
     let mut row = 0usize;
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
@@ -102,13 +107,7 @@ fn parse(filename: &str) -> Grid{
                     grid.plots.insert((row, col));
                     grid.start = (row as i64, col as i64);
                 }
-                '#' => {
-                    if (row + col) % 2 == 0 {
-                        even_rocks += 1;
-                    } else {
-                        odd_rocks += 1;
-                    }
-                },
+                '#' => {}
                 _ => unreachable!(),
             }
             cols = col;
@@ -118,7 +117,6 @@ fn parse(filename: &str) -> Grid{
         line.clear();
     }
     grid.rows = row;
-    println!("Odd rocks {}, even rocks {}", odd_rocks, even_rocks);
     grid
 }
 
@@ -133,10 +131,12 @@ impl Ord for State {
         // Notice that then we flip the ordering on costs.
         // In case of a tie we compare positions - this step is necessary
         // to make implementations of `PartialEq` and `Ord` consistent.
-        other.dist.cmp(&self.dist)
-            .then_with(|| self.pos.0.cmp(&other.pos.0)
+        other.dist.cmp(&self.dist).then_with(|| {
+            self.pos
+                .0
+                .cmp(&other.pos.0)
                 .then_with(|| self.pos.1.cmp(&other.pos.1))
-            )
+        })
     }
 }
 
@@ -146,34 +146,12 @@ impl PartialOrd for State {
     }
 }
 
-fn part_one(filename: &str) {
-    let grid = parse(filename);
-    let reachable = step_counter(&grid,  131*4 + 65, grid.start);
-    println!("Part one: {}", reachable);
-}
-
 #[allow(dead_code)]
 fn write_dists_to_file(distances: &HashMap<(i64, i64), usize>, grid: &Grid) {
-    let min_row = distances
-        .iter()
-        .map(|((row, _), _)| *row)
-        .min()
-        .unwrap();
-    let max_row = distances
-        .iter()
-        .map(|((row, _), _)| *row)
-        .max()
-        .unwrap();
-    let min_col = distances
-        .iter()
-        .map(|((_, col), _)| *col)
-        .min()
-        .unwrap();
-    let max_col = distances
-        .iter()
-        .map(|((_, col), _)| *col)
-        .max()
-        .unwrap();
+    let min_row = distances.iter().map(|((row, _), _)| *row).min().unwrap();
+    let max_row = distances.iter().map(|((row, _), _)| *row).max().unwrap();
+    let min_col = distances.iter().map(|((_, col), _)| *col).min().unwrap();
+    let max_col = distances.iter().map(|((_, col), _)| *col).max().unwrap();
     let mut file = File::create("dists.txt").unwrap();
     for row in min_row..=max_row {
         let mut line = String::new();
@@ -206,54 +184,149 @@ fn write_dists_to_file(distances: &HashMap<(i64, i64), usize>, grid: &Grid) {
         line.clear();
     }
 }
-fn step_counter(grid: &Grid, max_steps: usize, start_point: (i64, i64)) -> usize {
+/// The number of plots the elf can be on after exactly each of the given
+/// numbers of steps. Plots alternate colours like a chessboard, so these
+/// are the plots of the right colour within that many steps.
+fn step_counter(grid: &Grid, steps: &[usize]) -> Vec<u64> {
+    let max_steps = steps.iter().copied().max().unwrap_or(0);
+    let start_point = grid.start;
     let mut queue = BinaryHeap::new();
-    queue.push(State{ pos: start_point, dist: 0usize});
+    queue.push(State {
+        pos: start_point,
+        dist: 0usize,
+    });
     let mut distances = HashMap::from([(start_point, 0usize)]);
-    while let Some(State{pos: next, ..}) = queue.pop() {
-        for neighbor in grid.neighbors(next)
-            .into_iter()
-            .filter_map(|x| x)
-        {
-
+    while let Some(State { pos: next, .. }) = queue.pop() {
+        for neighbor in grid.neighbors(next).into_iter().flatten() {
             let new_dist = distances.get(&next).unwrap() + 1;
             let old_dist = distances.get(&neighbor).cloned().unwrap_or(usize::MAX);
             if new_dist < old_dist {
                 distances.insert(neighbor, new_dist);
-                if new_dist <= max_steps {
-                    queue.push(State { pos: neighbor, dist: new_dist });
+                if new_dist < max_steps {
+                    queue.push(State {
+                        pos: neighbor,
+                        dist: new_dist,
+                    });
                 }
             }
         }
     }
 
-    distances.iter()
-        .filter_map(|((r, h), dist)| {
-            (*dist <= max_steps && (r + h)% 2 == (grid.start.0 + grid.start.1 + (max_steps as i64 % 2)) % 2).then_some((*r, *h))
-        }).count()
+    steps
+        .iter()
+        .map(|steps| {
+            distances
+                .iter()
+                .filter(|((r, h), dist)| {
+                    **dist <= *steps
+                        && (r + h + start_point.0 + start_point.1).rem_euclid(2)
+                            == *steps as i64 % 2
+                })
+                .count() as u64
+        })
+        .collect()
+}
+
+/// The extrapolation needs the garden to be square with the start in the
+/// middle and nothing in the way along its row and column, so that the
+/// reachable area grows by a whole copy of the garden in every direction
+/// each `size` steps.
+fn validate(grid: &Grid) -> Result<(), String> {
+    if grid.rows != grid.cols {
+        return Err(format!(
+            "The garden is {}x{}, not square",
+            grid.rows, grid.cols
+        ));
+    }
+    let middle = (grid.rows / 2) as i64;
+    if grid.start != (middle, middle) {
+        return Err(format!("The start {:?} is not in the middle", grid.start));
+    }
+    let middle = middle as usize;
+    if !(0..grid.rows)
+        .all(|ix| grid.plots.contains(&(middle, ix)) && grid.plots.contains(&(ix, middle)))
+    {
+        return Err("The middle row and column are not clear".to_string());
+    }
+    Ok(())
+}
 
+/// Counts the plots reachable in exactly `steps` steps. With `steps = n +
+/// k * size` the count is a quadratic in `k`, so measure it for `k` = 0,
+/// 1 and 2 and extrapolate with Newton's forward differences. Small step
+/// counts are simply walked.
+fn reachable(grid: &Grid, steps: usize) -> Result<u64, String> {
+    if grid.rows == 0 || grid.cols == 0 {
+        return Err("The garden is empty".to_string());
+    }
+    let size = grid.rows;
+    let n = steps % size;
+    if steps <= n + 2 * size {
+        return Ok(step_counter(grid, &[steps])[0]);
+    }
+    validate(grid)?;
+    let samples = step_counter(grid, &[n, n + size, n + 2 * size]);
+    let (f0, f1, f2) = (samples[0] as i128, samples[1] as i128, samples[2] as i128);
+    let first = f1 - f0;
+    let second = f2 - 2 * f1 + f0;
+    let k = (steps / size) as i128;
+    let total = f0 + k * first + k * (k - 1) / 2 * second;
+    u64::try_from(total).map_err(|e| e.to_string())
 }
 
-fn part_two(scale: u64) {
-    //scale = 202300;
-    // 65 steps from:
-    // center: 7461
-    // top left: 7433
-    // top right: 7433
-    // bottom left: 7429
-    // bottom right: 7429
-    let side = 2 * scale + 1;
-    let square = side * side;
-    let centers = square / 2 + 1;
-    let off_centers = square - centers;
-    let steps = centers * 7461 + off_centers * 7433 + 2*scale*scale;
-    println!("Steps: {}", steps  );
-   // println!("Border: {}", steps - interior);
+fn part_one(filename: &str) {
+    let grid = parse(filename);
+    println!("Part one: {}", reachable(&grid, 64).unwrap());
+}
 
+fn part_two(filename: &str) {
+    let grid = parse(filename);
+    match reachable(&grid, 26501365) {
+        Ok(plots) => println!("Part two: {}", plots),
+        Err(e) => println!("Part two: {}", e),
+    }
 }
 
 fn main() {
     part_one("input.txt");
-    part_two(202300);
+    part_two("input.txt");
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_counter() {
+        let grid = parse("test.txt");
+        assert_eq!(step_counter(&grid, &[6, 10, 50, 100]), [16, 50, 1594, 6536]);
+        assert_eq!(reachable(&grid, 6), Ok(16));
+        assert_eq!(
+            reachable(&grid, 5000),
+            Err("The middle row and column are not clear".to_string())
+        );
+        let empty = Grid {
+            start: (0, 0),
+            plots: HashSet::new(),
+            rows: 0,
+            cols: 0,
+        };
+        assert_eq!(
+            reachable(&empty, 64),
+            Err("The garden is empty".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extrapolation() {
+        let grid = parse("input.txt");
+        assert_eq!(validate(&grid), Ok(()));
+        let size = grid.rows;
+        // one more copy of the garden than the fit was measured on
+        let steps = 65 + 3 * size;
+        assert_eq!(
+            reachable(&grid, steps),
+            Ok(step_counter(&grid, &[steps])[0])
+        );
+    }
+}
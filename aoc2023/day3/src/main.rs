@@ -1,109 +1,251 @@
-use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::str::FromStr;
 
-fn parse_input(filename: &str) -> Engine {
+type Pos = (usize, usize);
+
+fn parse_input(filename: &str) -> Schematic {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
 
-    let mut line_number = 0;
-    let mut next_word: Option<Word> = None;
-    let mut engine = Engine::default();
+    let mut schematic = Schematic::default();
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
             break;
         }
-        for (x, c) in line.chars().enumerate() {
-            if c.is_numeric() {
-                if let Some(word) = next_word.as_mut() {
-                    word.length += 1;
-                    word.val.push(c);
-                } else {
-                    next_word = Some(Word{head: (x, line_number), length: 1, val: c.into()});
-                }
-            } else {
-                if let Some(word) = next_word.take() {
-                    engine.words.push(word);
-                }
-                if c != '.' && c != '\n' {
-                    engine.symbols.insert((x, line_number, c));
-                }
-            }
+        let row = line.trim_end();
+        if !row.is_empty() {
+            schematic.push_row(row);
         }
-
         line.clear();
-        line_number += 1;
     }
-    engine
+    schematic
 }
 
-#[derive(Debug)]
-struct Word {
-    head: (usize, usize),
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    /// A digit of the part with this index
+    Part(usize),
+    Symbol(char),
+}
+
+/// A number written on the schematic
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Part {
+    /// Position of the first digit as (x, y)
+    head: Pos,
     length: usize,
-    val: String,
+    value: u64,
 }
 
-impl Word {
-    fn adjacent(&self, (x, y): (usize, usize)) -> Option<u64> {
-        let (x, y) = (x as i64, y as i64);
-        let (head_x, head_y) = (self.head.0 as i64, self.head.1 as i64);
-        if head_x - 1 <= x && x <= head_x + self.length as i64 && (y - head_y).abs() <= 1 {
-            Some(u64::from_str(&self.val).unwrap())
-        } else {
-            None
+/// The engine schematic as a grid where every digit points back to the
+/// part number it belongs to, so neighbors can be looked up directly.
+#[derive(Debug, Default)]
+struct Schematic {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    parts: Vec<Part>,
+}
+
+impl Schematic {
+    fn push_row(&mut self, row: &str) {
+        let y = self.height;
+        if y == 0 {
+            self.width = row.chars().count();
+        }
+        assert_eq!(row.chars().count(), self.width, "Rows differ in length");
+        let mut current: Option<usize> = None;
+        for (x, c) in row.chars().enumerate() {
+            let cell = match c.to_digit(10) {
+                Some(digit) => {
+                    let ix = *current.get_or_insert_with(|| {
+                        self.parts.push(Part {
+                            head: (x, y),
+                            length: 0,
+                            value: 0,
+                        });
+                        self.parts.len() - 1
+                    });
+                    let part = &mut self.parts[ix];
+                    part.length += 1;
+                    part.value = 10 * part.value + digit as u64;
+                    Cell::Part(ix)
+                }
+                None => {
+                    current = None;
+                    if c == '.' {
+                        Cell::Empty
+                    } else {
+                        Cell::Symbol(c)
+                    }
+                }
+            };
+            self.cells.push(cell);
         }
+        self.height += 1;
     }
-}
 
-#[derive(Default, Debug)]
-struct Engine {
-    words: Vec<Word>,
-    symbols: HashSet<(usize, usize, char)>,
-}
+    fn get(&self, (x, y): Pos) -> Cell {
+        self.cells[y * self.width + x]
+    }
 
-impl Engine {
-    fn part_one(&self) -> u64 {
-        let mut total = 0;
-        for word in &self.words {
-            for (x, y, _) in &self.symbols {
-                if let Some(val) = word.adjacent((*x, *y)) {
-                    total += val;
-                    break;
+    /// The cells surrounding a rectangle, clipped to the schematic
+    fn border(&self, (x, y): Pos, length: usize) -> impl Iterator<Item = Pos> + '_ {
+        let (left, right) = (
+            x.saturating_sub(1),
+            std::cmp::min(x + length, self.width - 1),
+        );
+        let (top, bottom) = (y.saturating_sub(1), std::cmp::min(y + 1, self.height - 1));
+        (top..=bottom)
+            .flat_map(move |row| (left..=right).map(move |col| (col, row)))
+            .filter(move |(col, row)| *row != y || *col < x || *col >= x + length)
+    }
+
+    /// The indices of the parts touching a cell, each listed once
+    fn parts_adjacent_to(&self, pos: Pos) -> Vec<usize> {
+        let mut parts = vec![];
+        for neighbor in self.border(pos, 1) {
+            if let Cell::Part(ix) = self.get(neighbor) {
+                if !parts.contains(&ix) {
+                    parts.push(ix);
                 }
             }
         }
-        total
+        parts
+    }
+
+    fn symbols_adjacent_to(&self, part: usize) -> Vec<(Pos, char)> {
+        let Part { head, length, .. } = self.parts[part];
+        self.border(head, length)
+            .filter_map(|pos| match self.get(pos) {
+                Cell::Symbol(c) => Some((pos, c)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn is_valid(&self, part: usize) -> bool {
+        !self.symbols_adjacent_to(part).is_empty()
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = (Pos, char)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, cell)| match cell {
+                Cell::Symbol(c) => Some(((ix % self.width, ix / self.width), *c)),
+                _ => None,
+            })
+    }
+
+    /// Symbols from the given set touching exactly `k` parts, along
+    /// with those parts
+    fn gears<'a>(
+        &'a self,
+        symbols: &'a [char],
+        k: usize,
+    ) -> impl Iterator<Item = (Pos, Vec<usize>)> + 'a {
+        self.symbols()
+            .filter(|(_, c)| symbols.contains(c))
+            .map(|(pos, _)| (pos, self.parts_adjacent_to(pos)))
+            .filter(move |(_, parts)| parts.len() == k)
+    }
+
+    fn part_one(&self) -> u64 {
+        (0..self.parts.len())
+            .filter(|ix| self.is_valid(*ix))
+            .map(|ix| self.parts[ix].value)
+            .sum()
     }
 
     fn part_two(&self) -> u64 {
-        let mut total = 0;
-        for (x, y, symbol) in  &self.symbols {
-            if *symbol == '*' {
-                let mut adjacent = 0;
-                let mut gear_ratio: u64 = 1;
-                for word in &self.words {
-                    if let Some(val) = word.adjacent((*x, *y)) {
-                        if adjacent > 2 {
-                            break;
+        self.gears(&['*'], 2)
+            .map(|(_, parts)| {
+                parts
+                    .iter()
+                    .map(|ix| self.parts[*ix].value)
+                    .product::<u64>()
+            })
+            .sum()
+    }
+}
+
+/// Render the schematic with valid part numbers in green, the other
+/// numbers in red and the symbols in bold.
+impl Display for Schematic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let valid: Vec<bool> = (0..self.parts.len()).map(|ix| self.is_valid(ix)).collect();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                match self.get((x, y)) {
+                    Cell::Empty => write!(f, ".")?,
+                    Cell::Symbol(c) => write!(f, "\x1b[1m{}\x1b[0m", c)?,
+                    Cell::Part(ix) => {
+                        let Part {
+                            head,
+                            length,
+                            value,
+                        } = self.parts[ix];
+                        if head == (x, y) {
+                            // keep any leading zeros so the columns line up
+                            let colour = if valid[ix] { 32 } else { 31 };
+                            write!(f, "\x1b[{}m{:0length$}\x1b[0m", colour, value)?;
                         }
-                        adjacent += 1;
-                        gear_ratio *= val;
                     }
                 }
-                if adjacent == 2 {
-                    total += gear_ratio;
-                }
             }
+            writeln!(f)?;
         }
-        total
+        Ok(())
     }
 }
 
 fn main() {
-    let engine = parse_input("input.txt");
-    println!("Part one: {}", engine.part_one());
-    println!("Part two: {}", engine.part_two());
+    let schematic = parse_input("input.txt");
+    println!("Part one: {}", schematic.part_one());
+    println!("Part two: {}", schematic.part_two());
+    if std::env::args().any(|arg| arg == "--render") {
+        print!("{}", schematic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schematic() {
+        let schematic = parse_input("test.txt");
+        assert_eq!(schematic.parts.len(), 10);
+        assert_eq!(schematic.part_one(), 4361);
+        assert_eq!(schematic.part_two(), 467835);
+
+        let star = schematic.parts_adjacent_to((3, 1));
+        let values: Vec<u64> = star.iter().map(|ix| schematic.parts[*ix].value).collect();
+        assert_eq!(values, vec![467, 35]);
+        let invalid: Vec<u64> = (0..schematic.parts.len())
+            .filter(|ix| !schematic.is_valid(*ix))
+            .map(|ix| schematic.parts[ix].value)
+            .collect();
+        assert_eq!(invalid, vec![114, 58]);
+        assert_eq!(schematic.symbols_adjacent_to(2), vec![((3, 1), '*')]);
+        // the lone star next to 617 is not a gear
+        assert_eq!(schematic.gears(&['*'], 1).count(), 1);
+        assert_eq!(schematic.gears(&['*', '#', '+', '$'], 1).count(), 4);
+    }
+
+    #[test]
+    fn test_render_leading_zeros() {
+        let mut schematic = Schematic::default();
+        schematic.push_row("007*.");
+        schematic.push_row(".....");
+        assert_eq!(schematic.parts[0].value, 7);
+        assert_eq!(
+            schematic.to_string(),
+            "\x1b[32m007\x1b[0m\x1b[1m*\x1b[0m.\n.....\n"
+        );
+    }
 }
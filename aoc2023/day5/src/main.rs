@@ -1,9 +1,7 @@
-use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
-
 #[derive(Debug, Clone)]
 struct MapRange {
     dest: u64,
@@ -15,7 +13,7 @@ impl MapRange {
     fn map(&self, num: u64) -> Option<u64> {
         if self.source <= num && num < self.source + self.range {
             Some((num - self.source) + self.dest)
-        }  else {
+        } else {
             None
         }
     }
@@ -23,14 +21,10 @@ impl MapRange {
 
 #[derive(Debug, Clone, Default)]
 struct Map {
-    ranges: Vec<MapRange>
+    ranges: Vec<MapRange>,
 }
 
 impl Map {
-    fn clear(&mut self) {
-        self.ranges.clear()
-    }
-
     fn map(&self, num: u64) -> u64 {
         for range in &self.ranges {
             if let Some(val) = range.map(num) {
@@ -41,49 +35,100 @@ impl Map {
     }
 }
 
-#[derive(Clone)]
-struct Maps {
-    seed_to_soil: Map,
-    soil_to_fertilizer: Map,
-    fertilizer_to_water: Map,
-    water_to_light: Map,
-    light_to_temperature: Map,
-    temperature_to_humidity: Map,
-    humidity_to_location: Map,
+/// A map together with the names from its `x-to-y map:` header
+#[derive(Debug, Clone)]
+struct Stage {
+    source: String,
+    dest: String,
+    map: Map,
+}
+
+/// The seeds and the chain of maps from seeds to wherever the last map
+/// leads, in the order they appear.
+#[derive(Debug, Clone, Default)]
+struct Almanac {
+    seeds: Vec<u64>,
+    stages: Vec<Stage>,
 }
 
-impl Maps {
+impl Almanac {
     fn map(&self, seed: u64) -> u64 {
-        self.humidity_to_location.map(
-            self.temperature_to_humidity.map(
-                self.light_to_temperature.map(
-                    self.water_to_light.map(
-                        self.fertilizer_to_water.map(
-                            self.soil_to_fertilizer.map(
-                                self.seed_to_soil.map(seed)
-
-                            )
-                        )
-                    )
-                )
-            )
-        )
+        self.stages
+            .iter()
+            .fold(seed, |num, stage| stage.map.map(num))
+    }
+
+    /// The seeds read as pairs of start and length
+    fn seed_ranges(&self) -> RangeSet {
+        self.seeds
+            .chunks(2)
+            .map(|pair| (pair[0], pair[0] + pair[1]))
+            .collect()
+    }
+
+    /// Everything the set is sent to by the whole chain
+    fn image(&self, set: &RangeSet) -> RangeSet {
+        self.stages.iter().fold(set.clone(), |set, stage| {
+            PiecewiseLinear::from(&stage.map).image(&set)
+        })
+    }
+
+    /// Everything that the whole chain sends into the set. This is the
+    /// inverse of [`Almanac::image`], e.g. from locations back to seeds.
+    fn preimage(&self, set: &RangeSet) -> RangeSet {
+        self.compose_all().preimage(set)
+    }
+
+    /// The whole chain as a single function
+    fn compose_all(&self) -> PiecewiseLinear {
+        self.stages
+            .iter()
+            .map(|stage| PiecewiseLinear::from(&stage.map))
+            .reduce(|acc, next| next.compose(acc))
+            .expect("The almanac has no maps")
+            .canonical()
+    }
+}
+
+/// A set of numbers stored as sorted, disjoint half open ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct RangeSet {
+    ranges: Vec<(u64, u64)>,
+}
+
+impl RangeSet {
+    fn min(&self) -> Option<u64> {
+        self.ranges.first().map(|(lower, _)| *lower)
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        self.ranges
+            .iter()
+            .flat_map(|(l1, u1)| {
+                other
+                    .ranges
+                    .iter()
+                    .filter_map(move |(l2, u2)| intersection(*l1, *u1, *l2, *u2))
+            })
+            .collect()
     }
+}
 
-    fn test_points(self) -> HashSet<u64> {
-        let piecewise = PiecewiseLinear::from(self.humidity_to_location)
-            .compose(self.temperature_to_humidity.into())
-            .compose(self.light_to_temperature.into())
-            .compose(self.water_to_light.into())
-            .compose(self.fertilizer_to_water.into())
-            .compose(self.soil_to_fertilizer.into())
-            .compose(self.seed_to_soil.into());
-        let mut test_points = HashSet::new();
-        for piece in piecewise.pieces.into_iter() {
-            test_points.insert(piece.upper);
-            test_points.insert(piece.lower);
+impl FromIterator<(u64, u64)> for RangeSet {
+    fn from_iter<I: IntoIterator<Item = (u64, u64)>>(iter: I) -> Self {
+        let mut ranges: Vec<_> = iter
+            .into_iter()
+            .filter(|(lower, upper)| lower < upper)
+            .collect();
+        ranges.sort();
+        let mut merged: Vec<(u64, u64)> = vec![];
+        for (lower, upper) in ranges {
+            match merged.last_mut() {
+                Some(last) if lower <= last.1 => last.1 = std::cmp::max(last.1, upper),
+                _ => merged.push((lower, upper)),
+            }
         }
-        test_points
+        Self { ranges: merged }
     }
 }
 
@@ -97,41 +142,37 @@ struct LinearPiece {
 }
 
 impl LinearPiece {
-
     /// self(other)
     fn compose(self, other: Self) -> Option<Self> {
-        let (lower, upper)  = if other.trans <= 0  {
+        let (lower, upper) = if other.trans <= 0 {
             (
-                u64::saturating_add(self.lower, other.trans.abs() as u64),
-                u64::saturating_add(self.upper, other.trans.abs() as u64),
+                u64::saturating_add(self.lower, other.trans.unsigned_abs()),
+                u64::saturating_add(self.upper, other.trans.unsigned_abs()),
             )
         } else {
             (
-                if self.lower < other.trans as u64 { 0 } else {self.lower - other.trans as u64},
-                if self.upper < other.trans as u64 { 0 } else {self.upper - other.trans as u64},
+                self.lower.saturating_sub(other.trans as u64),
+                self.upper.saturating_sub(other.trans as u64),
             )
         };
 
         let (lower, upper) = intersection(lower, upper, other.lower, other.upper)?;
-        if upper < lower {
-            panic!("Composition bug!")
-        }
-        if lower == upper {
-            None
-        } else {
-            Some(Self {
-                lower,
-                upper,
-                trans: self.trans + other.trans,
-            })
-        }
+        Some(Self {
+            lower,
+            upper,
+            trans: self.trans + other.trans,
+        })
+    }
 
+    /// Move a number by the translation, clamped to the `u64`s
+    fn shift(num: u64, trans: i64) -> u64 {
+        (num as i128 + trans as i128).clamp(0, u64::MAX as i128) as u64
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 struct PiecewiseLinear {
-    pieces: Vec<LinearPiece>
+    pieces: Vec<LinearPiece>,
 }
 
 impl PiecewiseLinear {
@@ -144,13 +185,59 @@ impl PiecewiseLinear {
                 }
             }
         }
-        Self{ pieces }
+        Self { pieces }
     }
 
-    #[cfg(test)]
     fn sort(&mut self) {
         self.pieces.sort_by_key(|p| p.lower);
     }
+
+    /// Sort the pieces and merge neighbors with the same translation,
+    /// so equal functions have equal pieces.
+    fn canonical(mut self) -> Self {
+        self.sort();
+        let mut pieces: Vec<LinearPiece> = vec![];
+        for piece in self.pieces {
+            match pieces.last_mut() {
+                Some(last) if last.upper == piece.lower && last.trans == piece.trans => {
+                    last.upper = piece.upper
+                }
+                _ => pieces.push(piece),
+            }
+        }
+        Self { pieces }
+    }
+
+    fn image(&self, set: &RangeSet) -> RangeSet {
+        self.pieces
+            .iter()
+            .flat_map(|piece| {
+                set.ranges.iter().filter_map(move |(lower, upper)| {
+                    let (lower, upper) = intersection(piece.lower, piece.upper, *lower, *upper)?;
+                    Some((
+                        LinearPiece::shift(lower, piece.trans),
+                        LinearPiece::shift(upper, piece.trans),
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    fn preimage(&self, set: &RangeSet) -> RangeSet {
+        self.pieces
+            .iter()
+            .flat_map(|piece| {
+                set.ranges.iter().filter_map(move |(lower, upper)| {
+                    intersection(
+                        piece.lower,
+                        piece.upper,
+                        LinearPiece::shift(*lower, -piece.trans),
+                        LinearPiece::shift(*upper, -piece.trans),
+                    )
+                })
+            })
+            .collect()
+    }
 }
 
 impl From<MapRange> for LinearPiece {
@@ -163,124 +250,132 @@ impl From<MapRange> for LinearPiece {
     }
 }
 
-impl From<Map> for PiecewiseLinear {
-    fn from(map: Map) -> Self {
-        let mut pieces: Vec<_> = map.ranges.into_iter().map(LinearPiece::from).collect();
-        let existing: HashSet<_> = pieces.iter().map(|p| (p.lower, p.upper)).collect();
+impl From<&Map> for PiecewiseLinear {
+    fn from(map: &Map) -> Self {
+        let mut pieces: Vec<_> = map.ranges.iter().cloned().map(LinearPiece::from).collect();
         pieces.sort_by_key(|p| p.lower);
-        let max = pieces.last().unwrap().upper;
-        let mut last_lower = 0;
-        let mut missing_ranges: Vec<_> = pieces.iter()
-            .filter_map(|piece|{
-                let next = (last_lower, piece.lower);
-                last_lower = piece.lower;
-                if next.0 != next.1 && !existing.contains(&next){
-                    Some(next)
-                } else {
-                    None
-                }
-        }).collect();
-        missing_ranges.push((max, u64::MAX));
-        let missing_ranges: Vec<_> = missing_ranges.into_iter().map(|(l, u)| LinearPiece{
-            lower: l,
-            upper: u,
-            trans: 0,
-        }).collect();
-        pieces.extend_from_slice(&missing_ranges);
+        // numbers not covered by any range are mapped to themselves
+        let mut last_upper = 0;
+        let mut missing_ranges = vec![];
+        for piece in &pieces {
+            if last_upper < piece.lower {
+                missing_ranges.push((last_upper, piece.lower));
+            }
+            last_upper = std::cmp::max(last_upper, piece.upper);
+        }
+        missing_ranges.push((last_upper, u64::MAX));
+        pieces.extend(
+            missing_ranges
+                .into_iter()
+                .filter(|(l, u)| l < u)
+                .map(|(l, u)| LinearPiece {
+                    lower: l,
+                    upper: u,
+                    trans: 0,
+                }),
+        );
         Self { pieces }
     }
 }
 
-fn intersection(l1: u64, u1: u64, l2: u64, u2: u64) -> Option<(u64, u64)> {
-    if l1 <= l2 && l2 <= u1 &&  u1 <= u2  {
-        Some((l2, u1))
-    } else if l2 <= l1 && l1 <= u2 && u2 <= u1 {
-        Some((l1, u2))
-    } else if l1 <= l2 && u2 <= u1 {
-        Some((l2, u2))
-    } else if l2 <= l1 && u1 <= u2 {
-        Some((l1, u1))
-    } else {
-        None
+impl From<Map> for PiecewiseLinear {
+    fn from(map: Map) -> Self {
+        Self::from(&map)
     }
 }
 
-fn parse_input(filename: &str) -> Maps {
+/// The overlap of two half open ranges, if it is not empty
+fn intersection(l1: u64, u1: u64, l2: u64, u2: u64) -> Option<(u64, u64)> {
+    let (lower, upper) = (std::cmp::max(l1, l2), std::cmp::min(u1, u2));
+    (lower < upper).then_some((lower, upper))
+}
+
+fn parse_input(filename: &str) -> Almanac {
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
     let mut line = String::new();
-    let mut maps = vec![];
-    let mut map = Map::default();
+    let mut almanac = Almanac::default();
     while let Ok(length) = reader.read_line(&mut line) {
         if length == 0 {
             break;
         }
-        if line.ends_with("map:\n") {
-            maps.push(map.clone());
-            map.clear();
-            line.clear();
-            continue;
-        } else {
-            let vals: Vec<_> =  line.trim().split(' ')
+        let trimmed = line.trim();
+        if let Some(seeds) = trimmed.strip_prefix("seeds:") {
+            almanac.seeds = seeds
+                .split_whitespace()
+                .map(|num| u64::from_str(num).unwrap())
+                .collect();
+        } else if let Some(header) = trimmed.strip_suffix(" map:") {
+            let (source, dest) = header.split_once("-to-").unwrap();
+            if let Some(last) = almanac.stages.last() {
+                assert_eq!(last.dest, source, "The maps do not form a chain");
+            }
+            almanac.stages.push(Stage {
+                source: source.to_string(),
+                dest: dest.to_string(),
+                map: Map::default(),
+            });
+        } else if !trimmed.is_empty() {
+            let vals: Vec<_> = trimmed
+                .split(' ')
                 .map(|num| u64::from_str(num).unwrap())
                 .collect();
             let vals: [u64; 3] = vals.try_into().unwrap();
-            map.ranges.push(MapRange{dest: vals[0], source: vals[1], range: vals[2]});
+            almanac
+                .stages
+                .last_mut()
+                .expect("Found a range before any map")
+                .map
+                .ranges
+                .push(MapRange {
+                    dest: vals[0],
+                    source: vals[1],
+                    range: vals[2],
+                });
         }
         line.clear()
     }
-    maps.remove(0);
-    maps.push(map);
-    Maps {
-        seed_to_soil: maps[0].clone(),
-        soil_to_fertilizer: maps[1].clone(),
-        fertilizer_to_water: maps[2].clone(),
-        water_to_light: maps[3].clone(),
-        light_to_temperature: maps[4].clone(),
-        temperature_to_humidity: maps[5].clone(),
-        humidity_to_location: maps[6].clone(),
-    }
+    almanac
 }
 
-fn part_one(test: bool) {
-    let (maps, seeds) = if test {
-        (parse_input("test.txt"), vec![79u64, 14, 55, 13])
-    } else {
-        (parse_input("input.txt"), vec![1972667147, 405592018, 1450194064, 27782252, 348350443, 61862174,
-            3911195009, 181169206, 626861593, 138786487, 2886966111, 275299008, 825403564, 478003391,
-            514585599, 6102091, 2526020300, 15491453, 3211013652, 546191739])
-    };
-    let answer = seeds.into_iter()
-        .map(|seed| maps.map(seed))
+fn part_one(filename: &str) {
+    let almanac = parse_input(filename);
+    let answer = almanac
+        .seeds
+        .iter()
+        .map(|seed| almanac.map(*seed))
         .min()
         .unwrap();
     println!("Part one: {}", answer);
 }
 
-fn part_two(test: bool) {
-    let (maps, seeds) = if test {
-        (parse_input("test.txt"), vec![(79u64, 14u64), (55, 13)])
-    } else {
-        (parse_input("input.txt"), vec![(1972667147, 405592018), (1450194064, 27782252), (348350443, 61862174),
-            (3911195009, 181169206), (626861593, 138786487), (2886966111, 275299008), (825403564, 478003391),
-            (514585599, 6102091), (2526020300, 15491453), (3211013652, 546191739)])
-    };
-    let mut test_seeds: HashSet<_> =  maps.clone()
-        .test_points()
-        .into_iter()
-        .filter(|point| seeds.iter().any(|(l, u)| l <= point && *point < *l + *u))
-        .collect();
-    for (range_start, range_len) in &seeds {
-        test_seeds.insert(*range_start);
-        test_seeds.insert(*range_start + *range_len - 1);
-    }
-    let ans = test_seeds.into_iter().map(|seed|  maps.map(seed)).min().unwrap();
-    println!("Part two: {}", ans);
+fn part_two(filename: &str) {
+    let almanac = parse_input(filename);
+    let seeds = almanac.seed_ranges();
+    let locations = almanac.image(&seeds);
+    let location = locations.min().unwrap();
+    println!("Part two: {}", location);
+    let seed = almanac
+        .preimage(&RangeSet::from_iter([(location, location + 1)]))
+        .intersection(&seeds)
+        .min()
+        .unwrap();
+    println!(
+        "Reached from {} {} via {}",
+        almanac.stages[0].source,
+        seed,
+        almanac
+            .stages
+            .iter()
+            .map(|stage| stage.dest.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
 }
 
 fn main() {
-    part_one(false);
-    part_two(false);
+    part_one("input.txt");
+    part_two("input.txt");
 }
 
 #[cfg(test)]
@@ -475,4 +570,65 @@ mod tests {
             },
         ]});
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_almanac() {
+        let almanac = parse_input("test.txt");
+        let names: Vec<&str> = almanac.stages.iter().map(|s| s.dest.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "soil",
+                "fertilizer",
+                "water",
+                "light",
+                "temperature",
+                "humidity",
+                "location"
+            ]
+        );
+        let locations: Vec<u64> = almanac.seeds.iter().map(|s| almanac.map(*s)).collect();
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+
+        let seeds = almanac.seed_ranges();
+        assert_eq!(seeds.ranges, vec![(55, 68), (79, 93)]);
+        let image = almanac.image(&seeds);
+        assert_eq!(image.min(), Some(46));
+        let composite = almanac.compose_all();
+        assert_eq!(composite.image(&seeds), image);
+        for seed in 0..120 {
+            let location = almanac.map(seed);
+            let piece = composite
+                .pieces
+                .iter()
+                .find(|p| p.lower <= seed && seed < p.upper)
+                .unwrap();
+            assert_eq!(LinearPiece::shift(seed, piece.trans), location);
+            assert!(almanac
+                .preimage(&RangeSet::from_iter([(location, location + 1)]))
+                .ranges
+                .iter()
+                .any(|(l, u)| *l <= seed && seed < *u));
+        }
+        // the composite is canonical
+        for pair in composite.pieces.windows(2) {
+            assert_eq!(pair[0].upper, pair[1].lower);
+            assert_ne!(pair[0].trans, pair[1].trans);
+        }
+        let seed = almanac
+            .preimage(&RangeSet::from_iter([(46, 47)]))
+            .intersection(&seeds);
+        assert_eq!(seed.ranges, vec![(82, 83)]);
+    }
+
+    #[test]
+    fn test_range_set() {
+        let set = RangeSet::from_iter([(5, 10), (0, 3), (3, 4), (8, 12), (20, 20)]);
+        assert_eq!(set.ranges, vec![(0, 4), (5, 12)]);
+        let other = RangeSet::from_iter([(2, 6), (11, 30)]);
+        assert_eq!(
+            set.intersection(&other).ranges,
+            vec![(2, 4), (5, 6), (11, 12)]
+        );
+    }
+}
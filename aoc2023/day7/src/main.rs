@@ -1,174 +1,125 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
-mod part_two;
+mod rules;
 
-#[repr(u8)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-enum CardType {
-    Two = 0,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Joker,
-    Queen,
-    King,
-    Ace
-}
+use rules::*;
 
-impl From<char> for CardType {
-    fn from(value: char) -> Self {
-        match value {
-            'A' => Self::Ace,
-            'K' => Self::King,
-            'Q' => Self::Queen,
-            'J' => Self::Joker,
-            'T' => Self::Ten,
-            '9' => Self::Nine,
-            '8' => Self::Eight,
-            '7' => Self::Seven,
-            '6' => Self::Six,
-            '5' => Self::Five,
-            '4' => Self::Four,
-            '3' => Self::Three,
-            '2' => Self::Two,
-            other => panic!("Unrecognized card char: {}", other)
+fn parse_input(filename: &str) -> Vec<(String, u64)> {
+    let file = File::open(filename).unwrap();
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    let mut bids = vec![];
+    while let Ok(length) = reader.read_line(&mut line) {
+        if length == 0 {
+            break;
         }
+        if let Some((hand, val)) = line.trim().split_once(' ') {
+            bids.push((hand.to_string(), u64::from_str(val).unwrap()));
+        }
+        line.clear();
     }
+    bids
 }
 
-impl PartialOrd for CardType {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some((*self as u8).cmp(&(*other as u8)))
-    }
-}
-
-impl Ord for CardType {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
-
-#[repr(u8)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum HandType {
-    High = 0,
-    OnePair,
-    TwoPair,
-    ThreeKind,
-    FullHouse,
-    FourKind,
-    FiveKind,
+fn part_one(filename: &str) {
+    let result = HandRules::standard().winnings(&parse_input(filename));
+    println!("Part one: {}", result);
 }
 
-impl PartialOrd for HandType {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some((*self as u8).cmp(&(*other as u8)))
-    }
+fn part_two(filename: &str) {
+    let result = HandRules::jokers().winnings(&parse_input(filename));
+    println!("Part two: {}", result);
 }
 
-impl Ord for HandType {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
+/// Part two again, but breaking ties by the biggest groups as in poker
+fn poker(filename: &str) {
+    let rules = HandRules {
+        tie_break: TieBreak::ByGroups,
+        ..HandRules::jokers()
+    };
+    println!("Poker ties: {}", rules.winnings(&parse_input(filename)));
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-struct Hand {
-    r#type: HandType,
-    cards: [CardType; 5],
+fn main() {
+    part_one("input.txt");
+    part_two("input.txt");
+    poker("input.txt");
 }
 
-impl FromStr for Hand {
-    type Err = String;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let cards: [CardType; 5] = s.chars().map(CardType::from).collect::<Vec<_>>().try_into().unwrap();
-        let card_count: HashMap<_, usize> = cards.iter().fold(HashMap::new(),  |mut acc, c| {
-            acc.entry(c).and_modify(|counter| *counter += 1).or_insert(1);
-            acc
-        });
-        let mut counts: Vec<_> = card_count.values().cloned().collect();
-        counts.sort();
-        let hand_type = if counts.len() == 1 {
-            HandType::FiveKind
-        } else if counts == vec![1, 4,] {
-            HandType::FourKind
-        } else if counts == vec![2, 3, ] {
-            HandType::FullHouse
-        } else if counts == vec![1, 1, 3] {
-            HandType::ThreeKind
-        } else if counts == vec![1, 2, 2] {
-            HandType::TwoPair
-        } else if counts == vec![1, 1, 1, 2] {
-            HandType::OnePair
-        } else {
-            HandType::High
+    /// Try every substitution for the wildcards
+    fn brute_force(rules: &HandRules, cards: &str) -> Vec<usize> {
+        let Some(ix) = cards.chars().position(|c| rules.wildcards.contains(&c)) else {
+            return rules.hand(cards).unwrap().shape;
         };
-        Ok(Self {
-            r#type: hand_type,
-            cards,
-        })
-    }
-}
-
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.r#type > other.r#type {
-            Some(Ordering::Greater)
-        } else if self.r#type < other.r#type {
-            Some(Ordering::Less)
-        } else {
-            if self.cards == other.cards {
-                return Some(Ordering::Equal)
-            }
-            for ix in 0usize..5 {
-                if self.cards[ix] > other.cards[ix] {
-                    return Some(Ordering::Greater)
-                } else if self.cards[ix] < other.cards[ix] {
-                    return Some(Ordering::Less)
-                }
-            }
-            None
-        }
+        let plain = HandRules {
+            wildcards: vec![],
+            ..rules.clone()
+        };
+        rules
+            .order
+            .iter()
+            .filter(|c| !rules.wildcards.contains(c))
+            .map(|c| {
+                let mut cards: Vec<char> = cards.chars().collect();
+                cards[ix] = *c;
+                let cards: String = cards.into_iter().collect();
+                let rest = brute_force(rules, &cards);
+                std::cmp::max(rest, plain.hand(&cards).unwrap().shape)
+            })
+            .max()
+            .unwrap()
     }
-}
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+    #[test]
+    fn test_winnings() {
+        let bids = parse_input("test.txt");
+        assert_eq!(HandRules::standard().winnings(&bids), 6440);
+        assert_eq!(HandRules::jokers().winnings(&bids), 5905);
+        let rules = HandRules::jokers();
+        assert_eq!(rules.hand("KTJJT").unwrap().shape, vec![4, 1]);
+        assert!(rules.hand("JKKK2").unwrap() < rules.hand("QQQQ2").unwrap());
+        assert!(rules.hand("JJJJJ").unwrap() > rules.hand("AAAAK").unwrap());
+        assert!(rules.hand("AAAA").is_err());
+        assert!(rules.hand("AAAAX").is_err());
     }
-}
 
-fn part_one(filename: &str) {
-    let file = File::open(filename).unwrap();
-    let mut reader = BufReader::new(file);
-    let mut line = String::new();
-    let mut hands = vec![];
-    while let Ok(length) = reader.read_line(&mut line) {
-        if length == 0 {
-            break;
+    #[test]
+    fn test_closed_form_jokers() {
+        let rules = HandRules {
+            order: "J2A3".chars().collect(),
+            wildcards: vec!['J', '3'],
+            ..HandRules::jokers()
+        };
+        for n in 0..4usize.pow(5) {
+            let cards: String = (0..5).map(|i| rules.order[n / 4usize.pow(i) % 4]).collect();
+            assert_eq!(
+                rules.hand(&cards).unwrap().shape,
+                brute_force(&rules, &cards)
+            );
         }
-        let [hand, val]: [&str; 2] = line.split(' ').collect::<Vec<_>>().try_into().unwrap();
-        let hand = Hand::from_str(hand).unwrap();
-        let val = u64::from_str(val.trim()).unwrap();
-        hands.push((hand, val));
-        line.clear();
     }
-    hands.sort_by_key(|(hand, _)| *hand );
-    let result = hands.iter().enumerate().map(|(ix, (_, val))| (ix as u64 + 1) * (*val)).sum::<u64>();
-    println!("Part one: {}", result);
-}
 
-fn main() {
-    part_one("input.txt");
-    part_two::part_two("input.txt");
+    #[test]
+    fn test_house_rules() {
+        let rules = HandRules {
+            order: "23456789TJQKA".chars().collect(),
+            wildcards: vec!['2', 'J'],
+            hand_size: 6,
+            tie_break: TieBreak::ByGroups,
+        };
+        assert_eq!(rules.hand("2J3456").unwrap().shape, vec![3, 1, 1, 1]);
+        assert_eq!(rules.hand("22JJ2J").unwrap().shape, vec![6]);
+        // a six of a kind beats a five of a kind
+        assert!(rules.hand("AAAAAK").unwrap() < rules.hand("333333").unwrap());
+        // the higher pair decides between two pairs
+        assert!(rules.hand("AKKQQ9").unwrap() < rules.hand("KKAAQ9").unwrap());
+        // three pairs beat two
+        assert!(rules.hand("TT99A3").unwrap() < rules.hand("QQ3344").unwrap());
+    }
 }
@@ -0,0 +1,112 @@
+use std::cmp::Reverse;
+
+/// How hands of the same type are ordered
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Compare the cards in the order they were dealt
+    AsDealt,
+    /// Compare the biggest groups first, as in poker
+    ByGroups,
+}
+
+/// Everything that decides how strong a hand is
+#[derive(Debug, Clone)]
+pub struct HandRules {
+    /// Cards from weakest to strongest
+    pub order: Vec<char>,
+    /// Cards that act as whichever card makes the hand strongest
+    pub wildcards: Vec<char>,
+    pub hand_size: usize,
+    pub tie_break: TieBreak,
+}
+
+/// A hand scored under some rules. The shape is the number of copies of
+/// each card from most to least, with the wildcards added to the biggest
+/// group. Comparing shapes lexicographically gives the usual order of
+/// five of a kind, four of a kind, full house, three of a kind, two pair,
+/// one pair and high card, and extends it to any hand size.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hand {
+    pub shape: Vec<usize>,
+    /// The ranks of the cards in tie breaking order
+    pub ranks: Vec<u8>,
+}
+
+impl HandRules {
+    /// The rules of part one
+    pub fn standard() -> Self {
+        Self {
+            order: "23456789TJQKA".chars().collect(),
+            wildcards: vec![],
+            hand_size: 5,
+            tie_break: TieBreak::AsDealt,
+        }
+    }
+
+    /// The rules of part two, where jacks are jokers and the weakest card
+    pub fn jokers() -> Self {
+        Self {
+            order: "J23456789TQKA".chars().collect(),
+            wildcards: vec!['J'],
+            ..Self::standard()
+        }
+    }
+
+    fn rank(&self, card: char) -> Result<u8, String> {
+        self.order
+            .iter()
+            .position(|c| *c == card)
+            .map(|rank| rank as u8)
+            .ok_or_else(|| format!("Unrecognized card char: {}", card))
+    }
+
+    pub fn hand(&self, cards: &str) -> Result<Hand, String> {
+        let ranks = cards
+            .chars()
+            .map(|c| self.rank(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        if ranks.len() != self.hand_size {
+            return Err(format!("Expected {} cards in {}", self.hand_size, cards));
+        }
+        let mut counts = vec![0usize; self.order.len()];
+        let mut wild = 0;
+        for (card, rank) in cards.chars().zip(&ranks) {
+            if self.wildcards.contains(&card) {
+                wild += 1;
+            } else {
+                counts[*rank as usize] += 1;
+            }
+        }
+        let mut shape: Vec<usize> = counts.iter().cloned().filter(|c| *c > 0).collect();
+        shape.sort_by_key(|c| Reverse(*c));
+        // the wildcards can do no better than joining the biggest group
+        match shape.first_mut() {
+            Some(biggest) => *biggest += wild,
+            None => shape.push(wild),
+        }
+
+        let ranks = match self.tie_break {
+            TieBreak::AsDealt => ranks,
+            TieBreak::ByGroups => {
+                let mut ranks = ranks;
+                ranks.sort_by_key(|rank| Reverse((counts[*rank as usize], *rank)));
+                ranks
+            }
+        };
+        Ok(Hand { shape, ranks })
+    }
+
+    /// Each bid times the rank of its hand among all the hands
+    pub fn winnings(&self, bids: &[(String, u64)]) -> u64 {
+        let mut hands: Vec<(Hand, u64)> = bids
+            .iter()
+            .map(|(cards, bid)| (self.hand(cards).unwrap(), *bid))
+            .collect();
+        hands.sort();
+        hands
+            .iter()
+            .enumerate()
+            .map(|(ix, (_, bid))| (ix as u64 + 1) * bid)
+            .sum()
+    }
+}